@@ -0,0 +1,55 @@
+//! Command line client for deployed counters
+//!
+//! ```text
+//! counter-cli --program-id <PROGRAM> init
+//! counter-cli --program-id <PROGRAM> increment <COUNTER>
+//! ```
+
+use std::{error::Error, str::FromStr};
+
+use simple_solana_program::{cli, client::CounterClient};
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signer},
+};
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let matches = cli::command().get_matches();
+
+    let url = matches.get_one::<String>("url").expect("url has a default");
+    let keypair_path = match matches.get_one::<String>("keypair") {
+        Some(path) => path.clone(),
+        None => format!("{}/.config/solana/id.json", std::env::var("HOME")?),
+    };
+    let program_id = Pubkey::from_str(matches.get_one::<String>("program-id").expect("required"))?;
+
+    let client = CounterClient::new(url, program_id);
+    let payer = read_keypair_file(&keypair_path)
+        .map_err(|err| format!("failed to read keypair {}: {}", keypair_path, err))?;
+
+    let (subcommand, sub_matches) = matches.subcommand().expect("subcommand is required");
+    let counter = match subcommand {
+        "init" => {
+            let counter = Keypair::new();
+            let signature = client.initialize(&payer, &counter)?;
+            println!("Signature: {}", signature);
+            println!("Created counter {}", counter.pubkey());
+            counter.pubkey()
+        }
+        "increment" | "decrement" => {
+            let counter = Pubkey::from_str(sub_matches.get_one::<String>("counter").expect("required"))?;
+            let signature = if subcommand == "increment" {
+                client.increment(&payer, &counter)?
+            } else {
+                client.decrement(&payer, &counter)?
+            };
+            println!("Signature: {}", signature);
+            counter
+        }
+        "get" => Pubkey::from_str(sub_matches.get_one::<String>("counter").expect("required"))?,
+        _ => unreachable!("clap rejects unknown subcommands"),
+    };
+
+    println!("Count: {}", client.fetch(&counter)?.count);
+    Ok(())
+}
@@ -0,0 +1,60 @@
+//! Argument parsing for the `counter-cli` binary
+
+use clap::{Arg, Command};
+
+/// Default RPC endpoint, a local test validator
+pub const DEFAULT_URL: &str = "http://localhost:8899";
+
+/// The `counter-cli` command line: global connection options plus one
+/// subcommand per counter operation
+pub fn command() -> Command<'static> {
+    Command::new("counter-cli")
+        .about("Create, change and read deployed counters")
+        .arg(
+            Arg::new("url")
+                .long("url")
+                .short('u')
+                .takes_value(true)
+                .default_value(DEFAULT_URL)
+                .help("RPC URL of the cluster"),
+        )
+        .arg(
+            Arg::new("keypair")
+                .long("keypair")
+                .short('k')
+                .takes_value(true)
+                .help("Keypair paying fees and acting as authority [default: ~/.config/solana/id.json]"),
+        )
+        .arg(
+            Arg::new("program-id")
+                .long("program-id")
+                .short('p')
+                .takes_value(true)
+                .required(true)
+                .help("Address of the deployed counter program"),
+        )
+        .subcommand_required(true)
+        .subcommand(Command::new("init").about("Create a new counter controlled by the keypair"))
+        .subcommand(
+            Command::new("increment")
+                .about("Increment a counter by its step")
+                .arg(counter_arg()),
+        )
+        .subcommand(
+            Command::new("decrement")
+                .about("Decrement a counter by its step")
+                .arg(counter_arg()),
+        )
+        .subcommand(
+            Command::new("get")
+                .about("Print a counter's current count")
+                .arg(counter_arg()),
+        )
+}
+
+/// The positional counter address taken by every subcommand but `init`
+fn counter_arg() -> Arg<'static> {
+    Arg::new("counter")
+        .required(true)
+        .help("Address of the counter account")
+}
@@ -0,0 +1,112 @@
+//! RPC client for deployed counters
+//! Wraps an `RpcClient` so applications can create, change and read counters
+//! without fetching blockhashes, signing and confirming transactions themselves
+
+use solana_client::{client_error::ClientError as RpcError, rpc_client::RpcClient};
+use solana_program::{instruction::Instruction, program_error::ProgramError, pubkey::Pubkey};
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    signature::{Keypair, Signature, Signer},
+    system_instruction,
+    transaction::Transaction,
+};
+use thiserror::Error;
+
+use crate::{decode_counter, instruction_builders, CounterAccount};
+
+/// Errors returned by `CounterClient`
+#[derive(Error, Debug)]
+pub enum ClientError {
+    /// The RPC request or the transaction failed
+    #[error("RPC error: {0}")]
+    Rpc(Box<RpcError>),
+    /// The fetched account does not hold a counter
+    #[error("Invalid counter account: {0}")]
+    InvalidAccount(#[from] ProgramError),
+}
+
+impl From<RpcError> for ClientError {
+    fn from(e: RpcError) -> Self {
+        ClientError::Rpc(Box::new(e))
+    }
+}
+
+/// Sends counter instructions to a cluster and reads counters back
+pub struct CounterClient {
+    rpc: RpcClient,
+    program_id: Pubkey,
+}
+
+impl CounterClient {
+    /// Connect to the counter program `program_id` through the RPC node at `rpc_url`,
+    /// waiting for transactions to be confirmed
+    pub fn new(rpc_url: impl ToString, program_id: Pubkey) -> Self {
+        Self::with_rpc_client(
+            RpcClient::new_with_commitment(rpc_url.to_string(), CommitmentConfig::confirmed()),
+            program_id,
+        )
+    }
+
+    /// Talk to the counter program `program_id` through an existing `RpcClient`
+    pub fn with_rpc_client(rpc: RpcClient, program_id: Pubkey) -> Self {
+        Self { rpc, program_id }
+    }
+
+    /// The underlying RPC client, for requests this client does not wrap
+    pub fn rpc(&self) -> &RpcClient {
+        &self.rpc
+    }
+
+    /// Allocate the `counter` account and initialize it with `payer` as its authority
+    pub fn initialize(&self, payer: &Keypair, counter: &Keypair) -> Result<Signature, ClientError> {
+        let rent = self
+            .rpc
+            .get_minimum_balance_for_rent_exemption(CounterAccount::LEN)?;
+        let create_account_ix = system_instruction::create_account(
+            &payer.pubkey(),
+            &counter.pubkey(),
+            rent,
+            CounterAccount::LEN as u64,
+            &self.program_id,
+        );
+        let initialize_ix =
+            instruction_builders::initialize(&self.program_id, &payer.pubkey(), &counter.pubkey());
+        self.submit(&[create_account_ix, initialize_ix], payer, &[counter])
+    }
+
+    /// Increment `counter` by its step, with `authority` signing and paying the fee
+    pub fn increment(&self, authority: &Keypair, counter: &Pubkey) -> Result<Signature, ClientError> {
+        let instruction = instruction_builders::increment(&self.program_id, &authority.pubkey(), counter);
+        self.submit(&[instruction], authority, &[])
+    }
+
+    /// Decrement `counter` by its step, with `authority` signing and paying the fee
+    pub fn decrement(&self, authority: &Keypair, counter: &Pubkey) -> Result<Signature, ClientError> {
+        let instruction = instruction_builders::decrement(&self.program_id, &authority.pubkey(), counter);
+        self.submit(&[instruction], authority, &[])
+    }
+
+    /// Fetch and decode the counter stored at `counter`
+    pub fn fetch(&self, counter: &Pubkey) -> Result<CounterAccount, ClientError> {
+        let account = self.rpc.get_account(counter)?;
+        Ok(decode_counter(&account.data)?)
+    }
+
+    /// Sign `instructions` with the payer and any extra signers, then send and confirm them
+    fn submit(
+        &self,
+        instructions: &[Instruction],
+        payer: &Keypair,
+        extra_signers: &[&Keypair],
+    ) -> Result<Signature, ClientError> {
+        let mut signers = vec![payer];
+        signers.extend_from_slice(extra_signers);
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&payer.pubkey()),
+            &signers,
+            self.rpc.get_latest_blockhash()?,
+        );
+        Ok(self.rpc.send_and_confirm_transaction(&transaction)?)
+    }
+}
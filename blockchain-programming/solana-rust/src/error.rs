@@ -0,0 +1,137 @@
+//! Program specific errors
+
+use solana_program::program_error::ProgramError;
+use thiserror::Error;
+
+/// Errors returned by the counter program
+/// These reach clients as `ProgramError::Custom(code)` where `code`
+/// is the variant's discriminant, so existing values must never change
+#[derive(Error, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CounterError {
+    /// The signer is not the counter's authority
+    #[error("Signer is not the counter authority")]
+    Unauthorized = 0,
+    /// The operation would take the count below zero
+    #[error("Counter underflow")]
+    Underflow = 1,
+    /// The operation would take the count above `u64::MAX`
+    #[error("Counter overflow")]
+    Overflow = 2,
+    /// The counter account already holds data
+    #[error("Counter account already initialized")]
+    AlreadyInitialized = 3,
+    /// The counter account has not been initialized
+    #[error("Counter account not initialized")]
+    NotInitialized = 4,
+    /// The operation would take the count above its configured maximum
+    #[error("Counter would exceed its maximum value")]
+    AboveMaximum = 5,
+    /// The operation would take the count below its configured minimum
+    #[error("Counter would drop below its minimum value")]
+    BelowMinimum = 6,
+    /// The requested minimum is above the requested maximum
+    #[error("Counter limits are invalid")]
+    InvalidLimits = 7,
+    /// The requested step is zero
+    #[error("Counter step must be non-zero")]
+    InvalidStep = 8,
+    /// Fewer multisig members signed than the threshold requires
+    #[error("Not enough multisig signers")]
+    NotEnoughSigners = 9,
+    /// The multisig threshold or member list is invalid
+    #[error("Invalid multisig configuration")]
+    InvalidMultisig = 10,
+    /// Only one of the milestone and its target program was given
+    #[error("Milestone and milestone target must be set together")]
+    InvalidMilestone = 11,
+    /// No named counter matches the requested name
+    #[error("Named counter not found")]
+    NotFound = 12,
+    /// A named counter with the requested name already exists
+    #[error("Named counter already exists")]
+    DuplicateName = 13,
+    /// The counter was already incremented or decremented in this slot
+    #[error("Counter already changed this slot")]
+    RateLimited = 14,
+    /// The counter's cooldown since its last change has not yet passed
+    #[error("Counter cooldown has not passed")]
+    CooldownActive = 15,
+    /// The requested cooldown is negative
+    #[error("Counter cooldown must not be negative")]
+    InvalidCooldown = 16,
+    /// There is no undoable change since the last undo
+    #[error("Nothing to undo")]
+    NothingToUndo = 17,
+    /// The counter has been permanently frozen
+    #[error("Counter is frozen")]
+    Frozen = 18,
+    /// The counter is paused by its authority
+    #[error("Counter is paused")]
+    Paused = 19,
+    /// The counter account was written with a layout version this program does not know
+    #[error("Unsupported counter account version")]
+    UnsupportedVersion = 20,
+    /// The counter does not hold the value the caller expected
+    #[error("Counter value does not match the expected value")]
+    Conflict = 21,
+    /// The account is already in the counter's authority set
+    #[error("Account is already an authority")]
+    DuplicateAuthority = 22,
+    /// Removing the account would leave the counter without an authority
+    #[error("Cannot remove the last authority")]
+    LastAuthority = 23,
+    /// The counter's authority set is full
+    #[error("Too many authorities")]
+    TooManyAuthorities = 24,
+    /// The account does not start with the counter discriminator
+    #[error("Account is not a counter")]
+    InvalidAccountType = 25,
+    /// The counter account is larger than `CounterAccount::LEN`
+    #[error("Counter account has the wrong size")]
+    InvalidAccountSize = 26,
+    /// A division by zero was requested
+    #[error("Division by zero")]
+    DivideByZero = 27,
+    /// The counter does not hold the asserted value
+    #[error("Counter assertion failed")]
+    AssertionFailed = 28,
+    /// The label is longer than `MAX_LABEL_LEN` bytes
+    #[error("Label too long")]
+    LabelTooLong = 29,
+    /// A list in the instruction or account would exceed its cap
+    #[error("Too many entries")]
+    TooLarge = 30,
+    /// The authority is a key nobody can sign for, such as the system program
+    #[error("Invalid authority")]
+    InvalidAuthority = 31,
+    /// A strict counter was sent a signer its instruction does not need
+    #[error("Unexpected signer")]
+    UnexpectedSigner = 32,
+    /// The reference counter of a conditional change is below its threshold
+    #[error("Condition not met")]
+    ConditionNotMet = 33,
+    /// The counter was reached again while one of its operations was still running
+    #[error("Reentrant call")]
+    Reentrancy = 34,
+    /// The counter's expiry has passed, leaving it read-only
+    #[error("Counter expired")]
+    Expired = 35,
+    /// A percentage change is above `MAX_BASIS_POINTS`
+    #[error("Invalid percentage")]
+    InvalidPercentage = 36,
+    /// New limits would leave the current count outside them
+    #[error("Value out of range")]
+    ValueOutOfRange = 37,
+    /// The instruction does not apply to counters of this kind
+    #[error("Unsupported counter kind")]
+    UnsupportedKind = 38,
+    /// A fixed-point scale is above `MAX_SCALE`
+    #[error("Invalid scale")]
+    InvalidScale = 39,
+}
+
+impl From<CounterError> for ProgramError {
+    fn from(e: CounterError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
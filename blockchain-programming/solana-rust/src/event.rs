@@ -0,0 +1,87 @@
+//! Structured events for off-chain indexers
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{entrypoint::ProgramResult, log::sol_log_data, pubkey::Pubkey};
+
+/// Events logged after each successful change to a counter
+/// They appear base64 encoded in `Program data:` log lines and decode as Borsh,
+/// giving indexers a stable format instead of parsing `msg!` text
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub enum CounterEvent {
+    /// A counter was created
+    Initialized {
+        /// The counter account
+        counter: Pubkey,
+        /// The starting count
+        count: u64,
+        /// The generation of the counter, telling re-created counters apart
+        nonce: u64,
+    },
+    /// A counter was incremented
+    Incremented {
+        /// The counter account
+        counter: Pubkey,
+        /// The count after the increment
+        count: u64,
+    },
+    /// A counter was decremented
+    Decremented {
+        /// The counter account
+        counter: Pubkey,
+        /// The count after the decrement
+        count: u64,
+    },
+    /// A counter with `auto_reset` passed one of its limits and cycled to the other
+    Cycled {
+        /// The counter account
+        counter: Pubkey,
+        /// The count after cycling
+        count: u64,
+    },
+    /// A counter's count was replaced outright, by a reset, a set, a scaling,
+    /// an undo or a batch
+    CountChanged {
+        /// The counter account
+        counter: Pubkey,
+        /// The count stored before the instruction, so any epoch reset or
+        /// decay it applied first is included in the change
+        old: u64,
+        /// The count after the change
+        new: u64,
+    },
+    /// Control of a counter passed to a new authority
+    AuthorityChanged {
+        /// The counter account
+        counter: Pubkey,
+        /// The authority before the change
+        old: Pubkey,
+        /// The authority after the change
+        new: Pubkey,
+    },
+}
+
+impl CounterEvent {
+    /// Log the event as Borsh encoded program data
+    pub fn emit(&self) -> ProgramResult {
+        sol_log_data(&[self.try_to_vec()?.as_slice()]);
+        Ok(())
+    }
+}
+
+/// Decode the events in a transaction's log messages
+/// Takes every `Program data:` line, including the `Program log: Program data:`
+/// form native program tests print, and skips lines that are not a Borsh
+/// encoded `CounterEvent`, such as data logged by other programs.
+#[cfg(feature = "test-utils")]
+pub fn extract_events(logs: &[String]) -> Vec<CounterEvent> {
+    use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+
+    logs.iter()
+        .filter_map(|line| {
+            line.strip_prefix("Program data: ")
+                .or_else(|| line.strip_prefix("Program log: Program data: "))
+        })
+        .filter_map(|data| BASE64.decode(data).ok())
+        .filter_map(|data| CounterEvent::try_from_slice(&data).ok())
+        .collect()
+}
@@ -0,0 +1,702 @@
+//! Instructions understood by the counter program
+
+#[cfg(feature = "schema")]
+use borsh::BorshSchema;
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{msg, program_error::ProgramError, pubkey::Pubkey};
+
+use crate::{CounterConfig, MultisigConfig};
+
+/// The instruction wrapped by `Simulate`, encoded exactly like a `CounterInstruction`
+/// The Borsh derives bound every field type on the enum itself, which a
+/// `Box<CounterInstruction>` field would make recursive, so this implements them by hand.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SimulatedInstruction(pub Box<CounterInstruction>);
+
+impl From<CounterInstruction> for SimulatedInstruction {
+    fn from(instruction: CounterInstruction) -> Self {
+        Self(Box::new(instruction))
+    }
+}
+
+impl BorshSerialize for SimulatedInstruction {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.0.serialize(writer)
+    }
+}
+
+impl BorshDeserialize for SimulatedInstruction {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        Ok(Self(Box::new(CounterInstruction::deserialize_reader(reader)?)))
+    }
+}
+
+#[cfg(feature = "schema")]
+impl BorshSchema for SimulatedInstruction {
+    fn add_definitions_recursively(
+        definitions: &mut std::collections::HashMap<borsh::schema::Declaration, borsh::schema::Definition>,
+    ) {
+        // Already added further up the same recursion through `CounterInstruction`
+        if definitions.contains_key(&Self::declaration()) {
+            return;
+        }
+        let fields = borsh::schema::Fields::UnnamedFields(vec![CounterInstruction::declaration()]);
+        Self::add_definition(Self::declaration(), borsh::schema::Definition::Struct { fields }, definitions);
+        CounterInstruction::add_definitions_recursively(definitions);
+    }
+
+    fn declaration() -> borsh::schema::Declaration {
+        "SimulatedInstruction".to_string()
+    }
+}
+
+/// Define program instructions
+/// This is like API endpoints in traditional apps
+// The BorshSchema derive copies each variant's fields, attributes included, into
+// helper structs that are never read, so every field allows the dead code
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+#[cfg_attr(feature = "schema", derive(BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CounterInstruction {
+    /// Initialize a new counter account
+    /// Accounts expected:
+    /// 0. `[signer]` The account that will pay for the account creation
+    /// 1. `[writable]` The counter account to create
+    /// 2. `[]` Optional: the config account, to apply the program defaults
+    Initialize,
+    
+    /// Increment the counter by its configured step
+    /// Accounts expected:
+    /// 0. `[signer]` The authority or delegate account
+    /// 1. `[writable]` The counter account to increment
+    /// 2. `[signer]` Optional: further multisig members, when the counter has a multisig
+    /// 3. `[]` Optional: the milestone target program, when this increment may cross the milestone
+    Increment,
+    
+    /// Decrement the counter by its configured step
+    /// Accounts expected:
+    /// 0. `[signer]` The authority or delegate account
+    /// 1. `[writable]` The counter account to decrement
+    /// 2. `[signer]` Optional: further multisig members, when the counter has a multisig
+    Decrement,
+
+    /// Reset the counter back to zero
+    /// Accounts expected:
+    /// 0. `[signer]` The authority account
+    /// 1. `[writable]` The counter account to reset
+    /// 2. `[signer]` Optional: further multisig members, when the counter has a multisig
+    Reset,
+
+    /// Set the counter to an arbitrary value
+    /// Accounts expected:
+    /// 0. `[signer]` The authority account
+    /// 1. `[writable]` The counter account to update
+    /// 2. `[signer]` Optional: further multisig members, when the counter has a multisig
+    SetValue {
+        /// The value to store in the counter
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        value: u64,
+    },
+
+    /// Increment the counter by an arbitrary amount
+    /// Accounts expected:
+    /// 0. `[signer]` The authority or delegate account
+    /// 1. `[writable]` The counter account to increment
+    /// 2. `[signer]` Optional: further multisig members, when the counter has a multisig
+    /// 3. `[]` Optional: the milestone target program, when this increment may cross the milestone
+    IncrementBy {
+        /// The amount to add to the counter
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        amount: u64,
+    },
+
+    /// Decrement the counter by an arbitrary amount
+    /// Accounts expected:
+    /// 0. `[signer]` The authority or delegate account
+    /// 1. `[writable]` The counter account to decrement
+    /// 2. `[signer]` Optional: further multisig members, when the counter has a multisig
+    DecrementBy {
+        /// The amount to subtract from the counter
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        amount: u64,
+    },
+
+    /// Hand control of the counter to a new authority
+    /// Accounts expected:
+    /// 0. `[signer]` The current authority account
+    /// 1. `[writable]` The counter account
+    /// 2. `[]` The new authority account
+    TransferAuthority,
+
+    /// Propose a new authority, or clear the current proposal with `None`
+    /// The current authority keeps full control until the proposal is accepted
+    /// Accounts expected:
+    /// 0. `[signer]` The current authority account
+    /// 1. `[writable]` The counter account
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_base58::new_authority"))]
+    ProposeAuthority {
+        /// The authority that may accept control of the counter
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        new_authority: Option<Pubkey>,
+    },
+
+    /// Accept a pending authority proposal
+    /// Accounts expected:
+    /// 0. `[signer]` The proposed authority account
+    /// 1. `[writable]` The counter account
+    AcceptAuthority,
+
+    /// Close the counter and reclaim its rent lamports
+    /// Accounts expected:
+    /// 0. `[signer]` The authority account
+    /// 1. `[writable]` The counter account to close
+    /// 2. `[writable]` The account receiving the lamports
+    CloseCounter,
+
+    /// Initialize a new counter account bounded by `min_value..=max_value`
+    /// The counter starts at `min_value`
+    /// Accounts expected:
+    /// 0. `[signer]` The account that will pay for the account creation
+    /// 1. `[writable]` The counter account to create
+    InitializeWithLimits {
+        /// The lowest value the counter may reach
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        min_value: u64,
+        /// The highest value the counter may reach
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        max_value: u64,
+    },
+
+    /// Initialize a new counter account with explicit settings
+    /// Accounts expected:
+    /// 0. `[signer]` The account that will pay for the account creation
+    /// 1. `[writable]` The counter account to create
+    InitializeWithConfig {
+        /// The settings for the new counter
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        config: CounterConfig,
+    },
+
+    /// Create and initialize a counter at the PDA derived from
+    /// `["counter", authority, seed]`
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The authority account, which pays for the account
+    /// 1. `[writable]` The counter PDA to create
+    /// 2. `[]` The system program
+    InitializePda {
+        /// Caller chosen seed distinguishing this authority's counters
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        seed: String,
+    },
+
+    /// Require a multisig for count changes, or return to single authority with `None`
+    /// While a multisig is set, replacing or removing it needs that multisig's approval too
+    /// Accounts expected:
+    /// 0. `[signer]` The authority account
+    /// 1. `[writable]` The counter account
+    /// 2. `[signer]` Optional: further multisig members, when the counter has a multisig
+    SetMultisig {
+        /// The signer set and threshold to require
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        multisig: Option<MultisigConfig>,
+    },
+
+    /// Allow a delegate to increment and decrement the counter
+    /// While a multisig is set, the delegate is bound by it like any other signer
+    /// Accounts expected:
+    /// 0. `[signer]` The authority account
+    /// 1. `[writable]` The counter account
+    /// 2. `[signer]` Optional: further multisig members, when the counter has a multisig
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_base58::delegate"))]
+    SetDelegate {
+        /// The account to delegate to, replacing any current delegate
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        delegate: Pubkey,
+    },
+
+    /// Remove the counter's delegate
+    /// Accounts expected:
+    /// 0. `[signer]` The authority account
+    /// 1. `[writable]` The counter account
+    /// 2. `[signer]` Optional: further multisig members, when the counter has a multisig
+    RevokeDelegate,
+
+    /// Initialize a new signed counter account starting at zero
+    /// Accounts expected:
+    /// 0. `[signer]` The account that will pay for the account creation
+    /// 1. `[writable]` The counter account to create
+    InitializeSigned,
+
+    /// Apply a list of deltas to the counter in order, all or nothing
+    /// Each step is checked like `IncrementBy` / `DecrementBy`
+    /// Accounts expected:
+    /// 0. `[signer]` The authority or delegate account
+    /// 1. `[writable]` The counter account to update
+    /// 2. `[signer]` Optional: further multisig members, when the counter has a multisig
+    Batch {
+        /// Signed amounts to add (positive) or subtract (negative), at most `MAX_BATCH_OPS`
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        ops: Vec<i64>,
+    },
+
+    /// Initialize an empty multi-counter account
+    /// Accounts expected:
+    /// 0. `[signer]` The account that will own the named counters
+    /// 1. `[writable]` The multi-counter account to initialize
+    InitializeMulti,
+
+    /// Add a counter starting at zero to a multi-counter account
+    /// The account is reallocated when the new counter does not fit. An account
+    /// holds at most `MAX_NAMED_COUNTERS` counters.
+    /// Accounts expected:
+    /// 0. `[signer]` The authority account
+    /// 1. `[writable]` The multi-counter account
+    /// 2. `[signer, writable]` Optional: the account paying rent for extra space, when growing
+    /// 3. `[]` Optional: the system program, when growing
+    AddNamedCounter {
+        /// Name of the new counter (at most `MultiCounterAccount::MAX_NAME_LEN` bytes)
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        name: String,
+    },
+
+    /// Increment a named counter by one
+    /// Accounts expected:
+    /// 0. `[signer]` The authority account
+    /// 1. `[writable]` The multi-counter account
+    IncrementNamed {
+        /// Name of the counter to increment
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        name: String,
+    },
+
+    /// Decrement a named counter by one
+    /// Accounts expected:
+    /// 0. `[signer]` The authority account
+    /// 1. `[writable]` The multi-counter account
+    DecrementNamed {
+        /// Name of the counter to decrement
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        name: String,
+    },
+
+    /// Revert the last Increment, Decrement or SetValue
+    /// Only the most recent change can be undone, and only once
+    /// Accounts expected:
+    /// 0. `[signer]` The authority account
+    /// 1. `[writable]` The counter account
+    /// 2. `[signer]` Optional: further multisig members, when the counter has a multisig
+    Undo,
+
+    /// Permanently freeze the counter; every later instruction on it fails, except
+    /// closing it and withdrawing its excess lamports
+    /// There is no way to unfreeze
+    /// Accounts expected:
+    /// 0. `[signer]` The authority account
+    /// 1. `[writable]` The counter account
+    Freeze,
+
+    /// Temporarily halt changes to the count; authority management still works
+    /// Accounts expected:
+    /// 0. `[signer]` The authority account
+    /// 1. `[writable]` The counter account
+    Pause,
+
+    /// Allow changes to the count again after `Pause`
+    /// Accounts expected:
+    /// 0. `[signer]` The authority account
+    /// 1. `[writable]` The counter account
+    Unpause,
+
+    /// Initialize a new counter account controlled by an authority other than the payer
+    /// Accounts expected:
+    /// 0. `[signer]` The account that will pay for the account creation
+    /// 1. `[writable]` The counter account to create
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_base58::authority"))]
+    InitializeWithAuthority {
+        /// The authority that will control the counter
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        authority: Pubkey,
+    },
+
+    /// Initialize a new counter account starting at `initial`
+    /// Accounts expected:
+    /// 0. `[signer]` The account that will pay for the account creation
+    /// 1. `[writable]` The counter account to create
+    InitializeWithValue {
+        /// The starting count
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        initial: u64,
+    },
+
+    /// Upgrade a counter stored in the original layout, a bare count and authority, to
+    /// the current one
+    /// Does nothing for a counter already at the current version.
+    /// Accounts expected:
+    /// 0. `[signer]` The authority account
+    /// 1. `[writable]` The counter account
+    /// 2. `[signer, writable]` Optional: payer for the extra rent when the account must grow
+    /// 3. `[]` Optional: the system program, required with the payer
+    Migrate,
+
+    /// Close the counter like `CloseCounter`, but only while its count equals `expected`
+    /// Accounts expected:
+    /// 0. `[signer]` The authority account
+    /// 1. `[writable]` The counter account to close
+    /// 2. `[writable]` The account receiving the lamports
+    CloseCounterIfEquals {
+        /// The count the counter must hold to be closed
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        expected: u64,
+    },
+
+    /// Set the counter to `new`, but only while its count equals `expected`
+    /// Accounts expected:
+    /// 0. `[signer]` The authority account
+    /// 1. `[writable]` The counter account to update
+    /// 2. `[signer]` Optional: further multisig members, when the counter has a multisig
+    CompareAndSet {
+        /// The count the counter must hold to be updated
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        expected: u64,
+        /// The value to store in the counter
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        new: u64,
+    },
+
+    /// Create the program config account; the signer becomes its admin
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The admin, paying for the account creation
+    /// 1. `[writable]` The config PDA
+    /// 2. `[]` The system program
+    InitConfig {
+        /// The `max_value` of counters created by a plain `Initialize`
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        default_max_value: u64,
+        /// The `cooldown_secs` of counters created by a plain `Initialize`
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        default_cooldown_secs: i64,
+    },
+
+    /// Change the program defaults
+    /// Accounts expected:
+    /// 0. `[signer]` The admin account
+    /// 1. `[writable]` The config PDA
+    UpdateConfig {
+        /// The `max_value` of counters created by a plain `Initialize`
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        default_max_value: u64,
+        /// The `cooldown_secs` of counters created by a plain `Initialize`
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        default_cooldown_secs: i64,
+    },
+
+    /// Let another account change the count like the authority
+    /// Accounts expected:
+    /// 0. `[signer]` A member of the authority set
+    /// 1. `[writable]` The counter account
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_base58::member"))]
+    AddAuthority {
+        /// The account to add to the authority set
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        member: Pubkey,
+    },
+
+    /// Remove an account from the authority set; the last one can't be removed
+    /// Removing `authority` itself hands its role to the oldest remaining member.
+    /// Accounts expected:
+    /// 0. `[signer]` A member of the authority set
+    /// 1. `[writable]` The counter account
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_base58::member"))]
+    RemoveAuthority {
+        /// The account to remove from the authority set
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        member: Pubkey,
+    },
+
+    /// Decrement the counter by up to `amount`, stopping at zero, or at `min_value`
+    /// when that is higher, instead of failing
+    /// Accounts expected:
+    /// 0. `[signer]` The authority or delegate account
+    /// 1. `[writable]` The counter account to decrement
+    /// 2. `[signer]` Optional: further multisig members, when the counter has a multisig
+    DecrementToZero {
+        /// The most to subtract from the counter
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        amount: u64,
+    },
+
+    /// Initialize like `Initialize`, succeeding without changes when the payer's
+    /// counter already exists, so retried transactions don't fail
+    /// Accounts expected:
+    /// 0. `[signer]` The account that will pay for the account creation
+    /// 1. `[writable]` The counter account to create
+    /// 2. `[]` Optional: the config account, to apply the program defaults
+    InitializeIfNeeded,
+
+    /// Multiply the counter by `factor`
+    /// Accounts expected:
+    /// 0. `[signer]` The authority or delegate account
+    /// 1. `[writable]` The counter account to update
+    /// 2. `[signer]` Optional: further multisig members, when the counter has a multisig
+    MultiplyBy {
+        /// The factor to multiply the counter by; 0 sets it to 0
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        factor: u64,
+    },
+
+    /// Divide the counter by `divisor`
+    /// Accounts expected:
+    /// 0. `[signer]` The authority or delegate account
+    /// 1. `[writable]` The counter account to update
+    /// 2. `[signer]` Optional: further multisig members, when the counter has a multisig
+    DivideBy {
+        /// The non-zero divisor
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        divisor: u64,
+        /// Round the quotient up instead of down
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        round_up: bool,
+    },
+
+    /// Fail unless the counter holds `expected`; changes nothing and needs no signer
+    /// Accounts expected:
+    /// 0. `[]` The counter account
+    AssertCount {
+        /// The count the counter must hold
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        expected: u64,
+    },
+
+    /// Move `amount` from one unsigned counter to another, changing neither if
+    /// either side would pass its limits
+    /// Accounts expected:
+    /// 0. `[signer]` The authority of both counters
+    /// 1. `[writable]` The counter to take the units from
+    /// 2. `[writable]` The counter to add the units to
+    /// 3. `[signer]` Optional: further multisig members, when the counters have a multisig
+    TransferUnits {
+        /// The number of units to move
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        amount: u64,
+    },
+
+    /// Initialize a new counter account named `label`
+    /// Accounts expected:
+    /// 0. `[signer]` The account that will pay for the account creation
+    /// 1. `[writable]` The counter account to create
+    InitializeWithLabel {
+        /// The counter's name, at most `MAX_LABEL_LEN` bytes
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        label: String,
+    },
+
+    /// Rename the counter (only the authority can do this)
+    /// Accounts expected:
+    /// 0. `[signer]` The authority account
+    /// 1. `[writable]` The counter account
+    SetLabel {
+        /// The new name, at most `MAX_LABEL_LEN` bytes (empty to clear it)
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        label: String,
+    },
+
+    /// Increment the counter by 2, like `IncrementBy { amount: 2 }`
+    /// Preferred over sending two `Increment`s, which costs two instructions' compute
+    /// and, when sent separately, two transaction fees.
+    /// Accounts expected:
+    /// 0. `[signer]` The authority or delegate account
+    /// 1. `[writable]` The counter account to increment
+    /// 2. `[signer]` Optional: further multisig members, when the counter has a multisig
+    /// 3. `[]` Optional: the milestone target program, when this increment may cross the milestone
+    DoubleIncrement,
+
+    /// Initialize `count` new counter accounts controlled by the payer at once
+    /// Fails without initializing any of them if one is not a valid new counter.
+    /// Accounts expected:
+    /// 0. `[signer]` The account that will pay for the account creation
+    /// 1. `[writable]` The counter accounts to create, `count` of them
+    InitializeMany {
+        /// The number of counter accounts following the payer
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        count: u8,
+    },
+
+    /// Create and initialize generation `nonce` of a PDA counter, at the address derived
+    /// from `["counter", authority, seed, nonce]` (little-endian)
+    /// Nonce 0 adds no seed and so creates the `InitializePda` counter.
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The authority account, which pays for the account
+    /// 1. `[writable]` The counter PDA to create
+    /// 2. `[]` The system program
+    InitializePdaWithNonce {
+        /// Caller chosen seed distinguishing this authority's counters
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        seed: String,
+        /// The generation of the counter
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        nonce: u64,
+    },
+
+    /// Reset the counter back to the count it was initialized with
+    /// Unlike `Reset`, a counter started with `InitializeWithValue` returns to that value.
+    /// Accounts expected:
+    /// 0. `[signer]` The authority account
+    /// 1. `[writable]` The counter account to reset
+    /// 2. `[signer]` Optional: further multisig members, when the counter has a multisig
+    ResetToInitial,
+
+    /// Return the counts of several counters as consecutive little-endian `u64`s in
+    /// the return data, in account order; changes nothing and needs no signer
+    /// Accounts expected:
+    /// 0. `[]` The counter accounts to read, at most `MAX_RETURN_DATA / 8` of them
+    ReadMany,
+
+    /// Create and initialize the counter at `index` in the authority's family of
+    /// counters, at the PDA derived from `["counter", authority, index]` (little-endian)
+    /// The address is the `InitializePda` one for a seed of the index's four bytes.
+    /// Accounts expected:
+    /// 0. `[signer, writable]` The authority account, which pays for the account
+    /// 1. `[writable]` The counter PDA to create
+    /// 2. `[]` The system program
+    InitializeIndexed {
+        /// The counter's position in the authority's family
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        index: u32,
+    },
+
+    /// Increment the counter by its configured step and return the new count as a
+    /// little-endian `u64` in the return data, for CPI callers
+    /// Accounts expected:
+    /// 0. `[signer]` The authority or delegate account
+    /// 1. `[writable]` The counter account to increment
+    /// 2. `[signer]` Optional: further multisig members, when the counter has a multisig
+    /// 3. `[]` Optional: the milestone target program, when this increment may cross the milestone
+    IncrementAndReturn,
+
+    /// Move the counter's lamports above the rent-exempt minimum to another account,
+    /// keeping the counter open
+    /// Accounts expected:
+    /// 0. `[signer]` The authority account
+    /// 1. `[writable]` The counter account
+    /// 2. `[writable]` The account receiving the lamports
+    WithdrawExcessLamports,
+
+    /// Initialize a new counter holding the count of an existing one, controlled by `authority`
+    /// The new counter has the source's kind and otherwise default settings.
+    /// Accounts expected:
+    /// 0. `[signer]` The account that will pay for the account creation
+    /// 1. `[]` The counter to copy the count from
+    /// 2. `[writable]` The counter account to create
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_base58::authority"))]
+    SnapshotTo {
+        /// The authority that will control the new counter
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        authority: Pubkey,
+    },
+
+    /// Increment the counter by its configured step, but only while another counter's
+    /// count is at least `threshold`
+    /// Accounts expected:
+    /// 0. `[]` The reference counter, which is only read
+    /// 1. `[signer]` The authority or delegate account
+    /// 2. `[writable]` The counter account to increment
+    /// 3. `[signer]` Optional: further multisig members, when the counter has a multisig
+    /// 4. `[]` Optional: the milestone target program, when this increment may cross the milestone
+    IncrementIf {
+        /// The lowest reference count at which the increment goes ahead
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        threshold: u64,
+    },
+
+    /// Log every field of the counter, for debugging; changes nothing and needs no signer
+    /// Accounts expected:
+    /// 0. `[]` The counter account
+    DebugDump,
+
+    /// Add `raw` units to a fixed-point counter, where one whole is `10^scale` units
+    /// Accounts expected:
+    /// 0. `[signer]` The authority or delegate account
+    /// 1. `[writable]` The counter account to increment
+    /// 2. `[signer]` Optional: further multisig members, when the counter has a multisig
+    /// 3. `[]` Optional: the milestone target program, when this increment may cross the milestone
+    IncrementFixed {
+        /// The raw units to add to the count
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        raw: u64,
+    },
+
+    /// Initialize one new counter per entry of `authorities`, each controlled by its entry
+    /// Fails without initializing any of them if one is not a valid new counter.
+    /// Accounts expected:
+    /// 0. `[signer]` The account that will pay for the account creation
+    /// 1. `[writable]` The counter accounts to create, exactly one per authority
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_base58::authorities"))]
+    InitializeManyWithAuthorities {
+        /// The authority of each counter account, in account order
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        authorities: Vec<Pubkey>,
+    },
+
+    /// Repair an inconsistent history: reset an out-of-range position and clear
+    /// entries outside the counter's limits
+    /// Accounts expected:
+    /// 0. `[signer]` The authority account
+    /// 1. `[writable]` The counter account
+    RepairHistory,
+
+    /// Increase the counter by a percentage of its current value, rounded down
+    /// Accounts expected:
+    /// 0. `[signer]` The authority or delegate account
+    /// 1. `[writable]` The counter account to update
+    /// 2. `[signer]` Optional: further multisig members, when the counter has a multisig
+    IncrementPercent {
+        /// The increase in hundredths of a percent, at most `MAX_BASIS_POINTS`
+        /// (10000 doubles the count)
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        basis_points: u16,
+    },
+
+    /// Run a count-changing instruction without storing it and report its outcome
+    /// as a `SimulationOutcome` in the return data, leaving the counter unchanged
+    /// Succeeds whether or not `inner` would. Nothing else happens either: no events
+    /// are emitted and no milestone callback is invoked.
+    /// Accounts expected: those of `inner`
+    Simulate {
+        /// The instruction to simulate: an increment, decrement, set, reset, scale, batch
+        /// or undo of a single counter
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        inner: SimulatedInstruction,
+    },
+
+    /// Replace an unsigned counter's limits, which must still hold the current count
+    /// Accounts expected:
+    /// 0. `[signer]` The authority account
+    /// 1. `[writable]` The counter account
+    UpdateLimits {
+        /// The new lowest value the counter may reach
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        min_value: u64,
+        /// The new highest value the counter may reach
+        #[cfg_attr(feature = "schema", allow(dead_code))]
+        max_value: u64,
+    },
+}
+
+impl CounterInstruction {
+    /// Decode instruction data, rejecting any bytes left over after the instruction
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        if input.is_empty() {
+            msg!("Error: Empty instruction data");
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let mut remaining = input;
+        let instruction = Self::deserialize(&mut remaining)
+            .map_err(|_| ProgramError::InvalidInstructionData)?;
+        if !remaining.is_empty() {
+            msg!("Error: {} unexpected trailing instruction bytes", remaining.len());
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(instruction)
+    }
+}
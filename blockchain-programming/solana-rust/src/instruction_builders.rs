@@ -0,0 +1,56 @@
+//! Builders for the program's instructions, for use by clients
+//! Each builder encodes the instruction data and lists the accounts in the
+//! order the program expects them
+
+use borsh::BorshSerialize;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+
+use crate::CounterInstruction;
+
+/// Build an instruction with the standard `[signer, writable counter]` accounts
+fn counter_instruction(
+    program_id: &Pubkey,
+    instruction: &CounterInstruction,
+    signer: AccountMeta,
+    counter: &Pubkey,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        *program_id,
+        &instruction.try_to_vec().expect("instruction serialization is infallible"),
+        vec![signer, AccountMeta::new(*counter, false)],
+    )
+}
+
+/// Build an `Initialize` instruction making `payer` the counter's authority
+/// The counter account must already be allocated and owned by the program
+pub fn initialize(program_id: &Pubkey, payer: &Pubkey, counter: &Pubkey) -> Instruction {
+    counter_instruction(
+        program_id,
+        &CounterInstruction::Initialize,
+        AccountMeta::new(*payer, true),
+        counter,
+    )
+}
+
+/// Build an `Increment` instruction signed by `authority`
+pub fn increment(program_id: &Pubkey, authority: &Pubkey, counter: &Pubkey) -> Instruction {
+    counter_instruction(
+        program_id,
+        &CounterInstruction::Increment,
+        AccountMeta::new_readonly(*authority, true),
+        counter,
+    )
+}
+
+/// Build a `Decrement` instruction signed by `authority`
+pub fn decrement(program_id: &Pubkey, authority: &Pubkey, counter: &Pubkey) -> Instruction {
+    counter_instruction(
+        program_id,
+        &CounterInstruction::Decrement,
+        AccountMeta::new_readonly(*authority, true),
+        counter,
+    )
+}
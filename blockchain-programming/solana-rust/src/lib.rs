@@ -1,175 +1,3290 @@
+
+#[cfg(feature = "schema")]
+use borsh::BorshSchema;
 use borsh::{BorshDeserialize, BorshSerialize};
+use bytemuck::{Pod, Zeroable};
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
     entrypoint,
     entrypoint::ProgramResult,
+    instruction::Instruction,
     msg,
+    program::{invoke, invoke_signed, set_return_data, MAX_RETURN_DATA},
     program_error::ProgramError,
-    pubkey::Pubkey,
+    pubkey::{Pubkey, MAX_SEED_LEN},
     rent::Rent,
-    sysvar::Sysvar,
+    system_instruction, system_program,
+    sysvar::{clock::Clock, Sysvar},
 };
 
-/// Define the type of state stored in accounts
-/// This is like a database schema in traditional apps
-#[derive(BorshSerialize, BorshDeserialize, Debug)]
-pub struct CounterAccount {
-    /// The current count value
-    pub count: u64,
-    /// The authority that can modify this counter
-    pub authority: Pubkey,
+pub mod error;
+pub mod event;
+mod instruction;
+#[cfg(feature = "client")]
+pub mod client;
+#[cfg(feature = "client")]
+pub mod instruction_builders;
+#[cfg(feature = "cli")]
+pub mod cli;
+#[cfg(feature = "schema")]
+pub mod schema;
+#[cfg(feature = "serde")]
+mod serde_base58;
+
+pub use error::CounterError;
+pub use event::CounterEvent;
+#[cfg(feature = "test-utils")]
+pub use event::extract_events;
+pub use instruction::{CounterInstruction, SimulatedInstruction};
+#[cfg(feature = "schema")]
+pub use schema::write_schema;
+
+/// Prefix of the seeds used to derive PDA counter addresses
+pub const COUNTER_SEED_PREFIX: &[u8] = b"counter";
+
+/// Most members a counter multisig may have
+pub const MAX_MULTISIG_SIGNERS: usize = 5;
+
+/// Number of recent values kept in a counter's history
+pub const HISTORY_LEN: usize = 8;
+
+/// Most accounts a counter's authority set may hold besides `authority`
+/// Counter accounts reserve room for a full set, so adding members never reallocates.
+pub const MAX_AUTHORITIES: usize = 5;
+
+/// Longest counter label, in bytes
+pub const MAX_LABEL_LEN: usize = 32;
+
+/// Most counters a multi-counter account may hold
+pub const MAX_NAMED_COUNTERS: usize = 64;
+
+/// Most deltas a single `Batch` instruction may apply
+pub const MAX_BATCH_OPS: usize = 64;
+
+/// Largest `IncrementPercent` change, in basis points (+100%)
+pub const MAX_BASIS_POINTS: u16 = 10_000;
+
+/// Most decimal places a fixed-point counter may have, all the digits of a `u64` but one
+pub const MAX_SCALE: u32 = 19;
+
+/// First bytes of every counter account, telling it apart from other account types
+/// The first 8 bytes of `sha256("account:CounterAccount")`, as Anchor would derive it.
+pub const COUNTER_DISCRIMINATOR: [u8; 8] = [164, 8, 153, 71, 8, 44, 93, 22];
+
+/// Seed of the program-wide config PDA
+pub const CONFIG_SEED: &[u8] = b"config";
+
+/// How a counter handles results outside its limits
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "schema", derive(BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ArithmeticMode {
+    /// Reject the operation with an error
+    #[default]
+    Checked,
+    /// Clamp the result to the nearest limit
+    Saturating,
+    /// Wrap around the `u64` boundary; configured limits are still enforced
+    Wrapping,
+}
+
+/// How a counter's `count` is interpreted
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "schema", derive(BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CounterKind {
+    /// `count` is a `u64` bounded by the configured limits
+    #[default]
+    Unsigned,
+    /// `count` holds the bits of an `i64` that may go negative
+    /// Limits and arithmetic mode do not apply; every change is checked
+    Signed,
+}
+
+/// A value the count can take -- the count itself, its limits and its history --
+/// kept apart from the other `u64`s around it such as sizes and amounts
+/// Borsh encodes it exactly like the `u64` it wraps, so stored counters keep their layout.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[derive(Pod, Zeroable)]
+#[repr(transparent)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct CounterValue(pub u64);
+
+// Described as the `u64` it encodes as, so schema clients see no wrapper type
+#[cfg(feature = "schema")]
+impl BorshSchema for CounterValue {
+    fn add_definitions_recursively(
+        _definitions: &mut std::collections::HashMap<borsh::schema::Declaration, borsh::schema::Definition>,
+    ) {
+    }
+
+    fn declaration() -> borsh::schema::Declaration {
+        u64::declaration()
+    }
+}
+
+impl CounterValue {
+    /// The value `amount` higher, or `None` on overflow
+    pub fn checked_add(self, amount: u64) -> Option<Self> {
+        self.0.checked_add(amount).map(Self)
+    }
+
+    /// The value `amount` lower, or `None` on underflow
+    pub fn checked_sub(self, amount: u64) -> Option<Self> {
+        self.0.checked_sub(amount).map(Self)
+    }
+}
+
+impl From<u64> for CounterValue {
+    fn from(value: u64) -> Self {
+        Self(value)
+    }
+}
+
+impl From<CounterValue> for u64 {
+    fn from(value: CounterValue) -> Self {
+        value.0
+    }
+}
+
+impl PartialEq<u64> for CounterValue {
+    fn eq(&self, other: &u64) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialEq<CounterValue> for u64 {
+    fn eq(&self, other: &CounterValue) -> bool {
+        *self == other.0
+    }
+}
+
+impl PartialOrd<u64> for CounterValue {
+    fn partial_cmp(&self, other: &u64) -> Option<std::cmp::Ordering> {
+        self.0.partial_cmp(other)
+    }
+}
+
+impl std::fmt::Display for CounterValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Seeds a PDA counter's address was derived from
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PdaSeeds {
+    /// The authority the address was derived from
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_base58"))]
+    pub authority: Pubkey,
+    /// The caller chosen seed (at most `MAX_SEED_LEN` bytes)
+    pub seed: Vec<u8>,
+}
+
+/// M-of-N signer set required to modify a counter's count
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MultisigConfig {
+    /// The members allowed to approve a change (at most `MAX_MULTISIG_SIGNERS`)
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_base58"))]
+    pub signers: Vec<Pubkey>,
+    /// How many distinct members must sign
+    pub threshold: u8,
+}
+
+/// Define the type of state stored in accounts
+/// This is like a database schema in traditional apps
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CounterAccount {
+    /// Always `COUNTER_DISCRIMINATOR`
+    pub discriminator: [u8; 8],
+    /// Layout version, right after the discriminator so old accounts can be recognized
+    pub version: u8,
+    /// The current count value
+    pub count: CounterValue,
+    /// The authority that can modify this counter
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_base58"))]
+    pub authority: Pubkey,
+    /// Unix timestamp of the last change to `count` (creation time until then)
+    pub last_updated: i64,
+    /// Number of operations that have modified `count`
+    pub update_count: u64,
+    /// The lowest value `count` may reach (0 for no limit)
+    pub min_value: CounterValue,
+    /// The highest value `count` may reach (`u64::MAX` for no limit)
+    pub max_value: CounterValue,
+    /// The amount added or subtracted by `Increment` and `Decrement`
+    pub step: u64,
+    /// How out-of-range results are handled
+    pub mode: ArithmeticMode,
+    /// Whether `count` is read as unsigned or signed
+    pub kind: CounterKind,
+    /// Bump seed of a PDA counter (0 for keypair counters)
+    pub bump: u8,
+    /// Whether an increment has already crossed `milestone` and notified its target
+    pub milestone_fired: bool,
+    /// Whether increments and decrements are limited to one per slot
+    pub rate_limited: bool,
+    /// Slot of the last rate limited increment or decrement
+    pub last_slot: u64,
+    /// Seconds that must pass after `last_updated` before the next change (0 for none)
+    pub cooldown_secs: i64,
+    /// Ring buffer of the values `count` took after its most recent changes
+    pub history: [CounterValue; HISTORY_LEN],
+    /// Slot in `history` the next value is written to
+    pub history_pos: u8,
+    /// Signed change made by the last Increment or Decrement, for `Undo` (0 when not undoable)
+    pub last_delta: i64,
+    /// Whether the counter has been permanently frozen against all changes
+    pub frozen: bool,
+    /// Whether changes to `count` are temporarily halted by the authority
+    pub paused: bool,
+    /// Set while an operation calls out to another program, so the counter cannot be
+    /// changed again until it returns
+    pub processing: bool,
+    /// Optional features in use, as `CounterAccount::FLAG_*` bits, so changes only run
+    /// the checks of the features whose bit is set
+    pub flags: u32,
+    /// An authority proposed by the current one, waiting to accept control
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_base58"))]
+    pub pending_authority: Option<Pubkey>,
+    /// Seeds of a PDA counter, re-checked against `bump` on every instruction
+    pub pda_seeds: Option<PdaSeeds>,
+    /// When set, count changes need this multisig instead of `authority` alone
+    pub multisig: Option<MultisigConfig>,
+    /// A third party allowed to increment and decrement on the authority's behalf, unless
+    /// a multisig is set, which binds the delegate like anyone else
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_base58"))]
+    pub delegate: Option<Pubkey>,
+    /// The count that triggers a callback to `milestone_target` when first reached
+    pub milestone: Option<u64>,
+    /// The program invoked when an increment crosses `milestone`
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_base58"))]
+    pub milestone_target: Option<Pubkey>,
+    /// The count before the last SetValue, for `Undo`
+    pub previous_count: Option<CounterValue>,
+    /// The count the counter started at
+    pub initial_count: CounterValue,
+    /// Whether the count cycles to the opposite limit instead of failing past one
+    pub auto_reset: bool,
+    /// Further accounts that may change the count like `authority`
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_base58"))]
+    pub authorities: Vec<Pubkey>,
+    /// Human-readable name shown by explorers (empty for none)
+    pub label: String,
+    /// Generation of the counter, chosen at creation so a counter re-created at
+    /// the same address can be told apart from the closed one
+    pub nonce: u64,
+    /// Return the count to `min_value` on the first change in each new epoch
+    pub epoch_window: bool,
+    /// Epoch of the current window, when `epoch_window` is set
+    pub last_epoch: u64,
+    /// Position in the authority's family of `InitializeIndexed` counters (0 otherwise)
+    pub index: u32,
+    /// Amount the count leaks per second since `last_updated`, taken off before each
+    /// change and never below `min_value` (0 for none)
+    pub decay_per_sec: u64,
+    /// Reject changes to the count that pass signers beyond the authority and multisig members
+    pub strict_signers: bool,
+    /// Decimal places of a fixed-point count, which then reads as `count / 10^scale`
+    pub scale: u32,
+    /// Unix timestamp from which the count can no longer change (0 for never)
+    pub expires_at: i64,
+}
+
+impl CounterAccount {
+    /// Space to allocate for a counter account
+    /// Sized for the largest serialized state: every optional field set and
+    /// the longest PDA seed
+    pub const LEN: usize = 8 // discriminator
+        + 1 // version
+        + 8 // count
+        + 32 // authority
+        + 8 // last_updated
+        + 8 // update_count
+        + 8 // min_value
+        + 8 // max_value
+        + 8 // step
+        + 1 // mode
+        + 1 // kind
+        + 1 // bump
+        + 1 // milestone_fired
+        + 1 // rate_limited
+        + 8 // last_slot
+        + 8 // cooldown_secs
+        + 8 * HISTORY_LEN // history
+        + 1 // history_pos
+        + 8 // last_delta
+        + 1 // frozen
+        + 1 // paused
+        + 1 // processing
+        + 4 // flags
+        + (1 + 32) // pending_authority
+        + (1 + 32 + 4 + MAX_SEED_LEN) // pda_seeds
+        + (1 + 4 + 32 * MAX_MULTISIG_SIGNERS + 1) // multisig
+        + (1 + 32) // delegate
+        + (1 + 8) // milestone
+        + (1 + 32) // milestone_target
+        + (1 + 8) // previous_count
+        + 8 // initial_count
+        + 1 // auto_reset
+        + (4 + 32 * MAX_AUTHORITIES) // authorities
+        + (4 + MAX_LABEL_LEN) // label
+        + 8 // nonce
+        + 1 // epoch_window
+        + 8 // last_epoch
+        + 4 // index
+        + 8 // decay_per_sec
+        + 1 // strict_signers
+        + 4 // scale
+        + 8; // expires_at
+
+    /// Layout version written by this program
+    pub const VERSION: u8 = 16;
+
+    /// `flags` bit of a counter limited to one change per slot
+    pub const FLAG_RATE_LIMIT: u32 = 1 << 0;
+    /// `flags` bit of a counter with a cooldown between changes
+    pub const FLAG_COOLDOWN: u32 = 1 << 1;
+    /// `flags` bit of a counter reset at the start of each epoch
+    pub const FLAG_EPOCH_WINDOW: u32 = 1 << 2;
+    /// `flags` bit of a counter whose count decays over time
+    pub const FLAG_DECAY: u32 = 1 << 3;
+    /// `flags` bit of a counter rejecting unexpected signers
+    pub const FLAG_STRICT_SIGNERS: u32 = 1 << 4;
+    /// `flags` bit of a counter with an expiry
+    pub const FLAG_EXPIRY: u32 = 1 << 5;
+
+    /// Version reported for the original layout: a bare count and authority, with no
+    /// discriminator or version byte
+    pub const BASELINE_VERSION: u8 = 0;
+
+    /// Size of a counter account in the original layout
+    pub const BASELINE_LEN: usize = 8 // count
+        + 32; // authority
+
+    /// Decode a counter from raw account data
+    /// Accounts are allocated for the largest layout, so any bytes past
+    /// the serialized state (e.g. an empty `pending_authority`) are ignored
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        Ok(Self::deserialize(&mut &data[..])?)
+    }
+
+    /// Decode a counter stored in the original or the current layout, upgraded to the
+    /// current one
+    /// An original counter keeps its count and authority; every other field starts
+    /// where a fresh unbounded counter's would.
+    pub fn upgrade(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.iter().all(|byte| *byte == 0) {
+            return Err(CounterError::NotInitialized.into());
+        }
+        if Self::stored_version(data)? == Self::VERSION {
+            return Self::unpack(data);
+        }
+        
+        let input = &mut &data[..];
+        Ok(Self {
+            discriminator: COUNTER_DISCRIMINATOR,
+            version: Self::VERSION,
+            count: CounterValue::deserialize(input)?,
+            authority: Pubkey::deserialize(input)?,
+            last_updated: 0,
+            update_count: 0,
+            min_value: CounterValue(0),
+            max_value: CounterValue(u64::MAX),
+            step: 1,
+            mode: ArithmeticMode::Checked,
+            kind: CounterKind::Unsigned,
+            bump: 0,
+            milestone_fired: false,
+            rate_limited: false,
+            last_slot: 0,
+            cooldown_secs: 0,
+            history: [CounterValue(0); HISTORY_LEN],
+            history_pos: 0,
+            last_delta: 0,
+            frozen: false,
+            paused: false,
+            processing: false,
+            flags: 0,
+            pending_authority: None,
+            pda_seeds: None,
+            multisig: None,
+            delegate: None,
+            milestone: None,
+            milestone_target: None,
+            previous_count: None,
+            initial_count: CounterValue(0),
+            auto_reset: false,
+            authorities: Vec::new(),
+            label: String::new(),
+            nonce: 0,
+            epoch_window: false,
+            last_epoch: 0,
+            index: 0,
+            decay_per_sec: 0,
+            strict_signers: false,
+            scale: 0,
+            expires_at: 0,
+        })
+    }
+
+    /// The layout version of stored counter data
+    /// An original-layout account, recognized by its size, reports `BASELINE_VERSION`.
+    /// Fails with `CounterError::InvalidAccountType` for data that is not a counter in any
+    /// layout and `CounterError::UnsupportedVersion` for a version this program does not know
+    pub fn stored_version(data: &[u8]) -> Result<u8, ProgramError> {
+        if data.len() == Self::BASELINE_LEN {
+            return Ok(Self::BASELINE_VERSION);
+        }
+        match data.strip_prefix(&COUNTER_DISCRIMINATOR) {
+            Some([version, ..]) if *version == Self::VERSION => Ok(*version),
+            Some(_) => Err(CounterError::UnsupportedVersion.into()),
+            None => Err(CounterError::InvalidAccountType.into()),
+        }
+    }
+
+    /// The `flags` bits of the optional features this counter's settings enable
+    pub fn feature_flags(&self) -> u32 {
+        [
+            (self.rate_limited, Self::FLAG_RATE_LIMIT),
+            (self.cooldown_secs > 0, Self::FLAG_COOLDOWN),
+            (self.epoch_window, Self::FLAG_EPOCH_WINDOW),
+            (self.decay_per_sec > 0, Self::FLAG_DECAY),
+            (self.strict_signers, Self::FLAG_STRICT_SIGNERS),
+            (self.expires_at > 0, Self::FLAG_EXPIRY),
+        ]
+        .iter()
+        .filter(|(enabled, _)| *enabled)
+        .fold(0, |flags, (_, flag)| flags | flag)
+    }
+
+    /// Whether the optional feature `flag` is enabled
+    fn has_flag(&self, flag: u32) -> bool {
+        self.flags & flag != 0
+    }
+
+    /// The recorded history, oldest value first
+    /// Holds one value per change, up to the last `HISTORY_LEN` changes
+    pub fn history_in_order(&self) -> Vec<CounterValue> {
+        let recorded = self.update_count.min(HISTORY_LEN as u64) as usize;
+        let start = self.history_pos as usize + HISTORY_LEN - recorded;
+        (start..start + recorded)
+            .map(|index| self.history[index % HISTORY_LEN])
+            .collect()
+    }
+
+    /// Bring an inconsistent history back into a valid state, returning the number of fixes
+    /// An out-of-range position is reset to where `update_count` changes would have left
+    /// it, and entries an unsigned counter could never have held within its limits are
+    /// cleared to zero.
+    fn sanitize_history(&mut self) -> usize {
+        let mut fixes = 0;
+        if self.history_pos as usize >= HISTORY_LEN {
+            let pos = (self.update_count % HISTORY_LEN as u64) as u8;
+            msg!("History position {} out of range, reset to {}", self.history_pos, pos);
+            self.history_pos = pos;
+            fixes += 1;
+        }
+        if self.kind == CounterKind::Unsigned {
+            for (index, value) in self.history.iter_mut().enumerate() {
+                if *value != 0 && (*value < self.min_value || *value > self.max_value) {
+                    msg!("History entry {} held {} outside the limits, cleared", index, value);
+                    *value = CounterValue(0);
+                    fixes += 1;
+                }
+            }
+        }
+        fixes
+    }
+
+    /// Append the current count to the history, overwriting the oldest value when full
+    fn push_history(&mut self) {
+        let pos = self.history_pos as usize % HISTORY_LEN;
+        self.history[pos] = self.count;
+        self.history_pos = ((pos + 1) % HISTORY_LEN) as u8;
+    }
+
+    /// Whether `key` is `authority` or a member of `authorities`
+    pub fn is_authority(&self, key: &Pubkey) -> bool {
+        self.authority == *key || self.authorities.contains(key)
+    }
+
+    /// The member of `authorities` matching `key`, or `authority` when none does
+    fn claimed_authority(&self, key: &Pubkey) -> &Pubkey {
+        self.authorities.iter().find(|member| *member == key).unwrap_or(&self.authority)
+    }
+
+    /// The count of a `Signed` counter
+    pub fn signed_count(&self) -> i64 {
+        self.count.0 as i64
+    }
+
+    /// The signed change from `old` to the current count, as actually applied after
+    /// clamping (0 when it doesn't fit an `i64`)
+    /// Signed and wrapping counters take the difference modulo 2^64, so stepping back
+    /// by it wraps to `old` again.
+    fn delta_since(&self, old: CounterValue) -> i64 {
+        match (self.kind, self.mode) {
+            (CounterKind::Signed, _) | (CounterKind::Unsigned, ArithmeticMode::Wrapping) => {
+                self.count.0.wrapping_sub(old.0) as i64
+            }
+            _ => i64::try_from(self.count.0 as i128 - old.0 as i128).unwrap_or(0),
+        }
+    }
+
+    /// The value `count + amount` would take, honoring limits and arithmetic mode
+    fn added(&self, amount: u64) -> Result<CounterValue, CounterError> {
+        if self.kind == CounterKind::Signed {
+            let amount = i64::try_from(amount).map_err(|_| CounterError::Overflow)?;
+            let new_count = self.signed_count().checked_add(amount).ok_or(CounterError::Overflow)?;
+            return Ok(CounterValue(new_count as u64));
+        }
+        let new_count = match self.mode {
+            ArithmeticMode::Checked => self.count.checked_add(amount).ok_or(CounterError::Overflow)?,
+            ArithmeticMode::Saturating => {
+                return Ok(CounterValue(self.count.0.saturating_add(amount)).min(self.max_value))
+            }
+            ArithmeticMode::Wrapping => CounterValue(self.count.0.wrapping_add(amount)),
+        };
+        self.check_limits(new_count)?;
+        Ok(new_count)
+    }
+
+    /// The value `count + amount` would take, cycling to `min_value` past the top
+    /// when `auto_reset` is set; the flag tells whether it cycled
+    fn advanced(&self, amount: u64) -> Result<(CounterValue, bool), CounterError> {
+        match self.added(amount) {
+            Err(CounterError::AboveMaximum | CounterError::Overflow) if self.auto_reset => {
+                Ok((self.min_value, true))
+            }
+            result => result.map(|count| (count, false)),
+        }
+    }
+
+    /// The value `count - amount` would take, cycling to `max_value` past the bottom
+    /// when `auto_reset` is set; the flag tells whether it cycled
+    fn retreated(&self, amount: u64) -> Result<(CounterValue, bool), CounterError> {
+        match self.subtracted(amount) {
+            Err(CounterError::BelowMinimum | CounterError::Underflow) if self.auto_reset => {
+                Ok((self.max_value, true))
+            }
+            result => result.map(|count| (count, false)),
+        }
+    }
+
+    /// The value `count - amount` would take, honoring limits and arithmetic mode
+    fn subtracted(&self, amount: u64) -> Result<CounterValue, CounterError> {
+        if self.kind == CounterKind::Signed {
+            let amount = i64::try_from(amount).map_err(|_| CounterError::Underflow)?;
+            let new_count = self.signed_count().checked_sub(amount).ok_or(CounterError::Underflow)?;
+            return Ok(CounterValue(new_count as u64));
+        }
+        let new_count = match self.mode {
+            ArithmeticMode::Checked => self.count.checked_sub(amount).ok_or(CounterError::Underflow)?,
+            ArithmeticMode::Saturating => {
+                return Ok(CounterValue(self.count.0.saturating_sub(amount)).max(self.min_value))
+            }
+            ArithmeticMode::Wrapping => CounterValue(self.count.0.wrapping_sub(amount)),
+        };
+        self.check_limits(new_count)?;
+        Ok(new_count)
+    }
+
+    /// The value `count * factor` would take, honoring limits
+    fn multiplied(&self, factor: u64) -> Result<CounterValue, CounterError> {
+        if self.kind == CounterKind::Signed {
+            let factor = i64::try_from(factor).map_err(|_| CounterError::Overflow)?;
+            let new_count = self.signed_count().checked_mul(factor).ok_or(CounterError::Overflow)?;
+            return Ok(CounterValue(new_count as u64));
+        }
+        let new_count = CounterValue(self.count.0.checked_mul(factor).ok_or(CounterError::Overflow)?);
+        self.check_limits(new_count)?;
+        Ok(new_count)
+    }
+
+    /// The value `count + count * basis_points / 10_000` would take, rounded down,
+    /// honoring limits
+    fn grown(&self, basis_points: u16) -> Result<CounterValue, CounterError> {
+        if basis_points > MAX_BASIS_POINTS {
+            msg!("Error: {} basis points is above the maximum of {}", basis_points, MAX_BASIS_POINTS);
+            return Err(CounterError::InvalidPercentage);
+        }
+        if self.kind == CounterKind::Signed {
+            let growth = self.signed_count()
+                .checked_mul(basis_points as i64)
+                .ok_or(CounterError::Overflow)?
+                / 10_000;
+            let new_count = self.signed_count().checked_add(growth).ok_or(CounterError::Overflow)?;
+            return Ok(CounterValue(new_count as u64));
+        }
+        let growth = self.count.0
+            .checked_mul(basis_points as u64)
+            .ok_or(CounterError::Overflow)?
+            / 10_000;
+        let new_count = self.count.checked_add(growth).ok_or(CounterError::Overflow)?;
+        self.check_limits(new_count)?;
+        Ok(new_count)
+    }
+
+    /// The value `count / divisor` would take, rounded down or up, honoring limits
+    fn divided(&self, divisor: u64, round_up: bool) -> Result<CounterValue, CounterError> {
+        if divisor == 0 {
+            msg!("Error: Cannot divide by zero");
+            return Err(CounterError::DivideByZero);
+        }
+        if self.kind == CounterKind::Signed {
+            // Widen so negating the dividend can't overflow; the quotient always fits back
+            let (count, divisor) = (self.signed_count() as i128, divisor as i128);
+            let new_count = if round_up {
+                -(-count).div_euclid(divisor)
+            } else {
+                count.div_euclid(divisor)
+            };
+            return Ok(CounterValue(new_count as i64 as u64));
+        }
+        let new_count = CounterValue(if round_up {
+            self.count.0.div_ceil(divisor)
+        } else {
+            self.count.0 / divisor
+        });
+        self.check_limits(new_count)?;
+        Ok(new_count)
+    }
+
+    /// Verify a PDA counter lives at the address derived from its stored seeds and bump
+    fn verify_address(&self, program_id: &Pubkey, address: &Pubkey) -> ProgramResult {
+        if let Some(pda_seeds) = &self.pda_seeds {
+            let nonce = self.nonce.to_le_bytes();
+            let bump = [self.bump];
+            let mut seeds = counter_generation_seeds(&pda_seeds.authority, &pda_seeds.seed, &nonce);
+            seeds.push(&bump);
+            let expected_address = Pubkey::create_program_address(&seeds, program_id)?;
+            if expected_address != *address {
+                msg!("Error: Counter account does not match derived address {}", expected_address);
+                return Err(ProgramError::InvalidSeeds);
+            }
+        }
+        Ok(())
+    }
+
+    /// Verify `value` lies within `min_value..=max_value`
+    fn check_limits(&self, value: CounterValue) -> Result<(), CounterError> {
+        if value < self.min_value {
+            msg!("Error: Counter would drop below min value {}", self.min_value);
+            return Err(CounterError::BelowMinimum);
+        }
+        if value > self.max_value {
+            msg!("Error: Counter would exceed max value {}", self.max_value);
+            return Err(CounterError::AboveMaximum);
+        }
+        Ok(())
+    }
+}
+
+/// Zero-copy view of the fixed-size fields at the front of a stored `CounterAccount`
+/// Mirrors the Borsh layout byte for byte, so it can be cast straight from
+/// account data; enums and bools are kept as their raw `u8` encoding.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub struct CounterData {
+    pub discriminator: [u8; 8],
+    pub version: u8,
+    pub count: CounterValue,
+    pub authority: Pubkey,
+    pub last_updated: i64,
+    pub update_count: u64,
+    pub min_value: CounterValue,
+    pub max_value: CounterValue,
+    pub step: u64,
+    pub mode: u8,
+    pub kind: u8,
+    pub bump: u8,
+    pub milestone_fired: u8,
+    pub rate_limited: u8,
+    pub last_slot: u64,
+    pub cooldown_secs: i64,
+    pub history: [CounterValue; HISTORY_LEN],
+    pub history_pos: u8,
+    pub last_delta: i64,
+    pub frozen: u8,
+    pub paused: u8,
+    pub processing: u8,
+    pub flags: u32,
+}
+
+impl CounterData {
+    /// Bytes of the optional fields following the fixed ones when none of them is set
+    const EMPTY_OPTIONS: [u8; 7] = [0; 7];
+
+    /// Cast the front of raw account data, if it is long enough
+    pub fn load(data: &[u8]) -> Option<&Self> {
+        data.get(..std::mem::size_of::<Self>()).map(bytemuck::from_bytes)
+    }
+
+    /// Cast the front of raw account data for modification, if it is long enough
+    pub fn load_mut(data: &mut [u8]) -> Option<&mut Self> {
+        data.get_mut(..std::mem::size_of::<Self>()).map(bytemuck::from_bytes_mut)
+    }
+
+    /// Whether `data` holds a correctly sized current-version counter whose increments
+    /// need none of the checks beyond the authority signature and limits: checked
+    /// unsigned arithmetic, no optional settings or features, not paused or frozen, and
+    /// no operation in progress
+    fn is_plain(data: &[u8]) -> bool {
+        let Some(counter) = Self::load(data) else {
+            return false;
+        };
+        let options = &data[std::mem::size_of::<Self>()..];
+        data.len() == CounterAccount::LEN
+            && counter.discriminator == COUNTER_DISCRIMINATOR
+            && counter.version == CounterAccount::VERSION
+            && options.starts_with(&Self::EMPTY_OPTIONS)
+            && counter.mode == ArithmeticMode::Checked as u8
+            && counter.kind == CounterKind::Unsigned as u8
+            && counter.frozen == 0
+            && counter.paused == 0
+            && counter.processing == 0
+            && counter.flags == 0
+    }
+}
+
+/// Several named counters sharing one account and authority
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct MultiCounterAccount {
+    /// The authority that can add and modify the counters
+    pub authority: Pubkey,
+    /// Counter values keyed by name, in the order they were added
+    pub counters: Vec<(String, u64)>,
+}
+
+impl MultiCounterAccount {
+    /// Longest allowed counter name, in bytes
+    pub const MAX_NAME_LEN: usize = 32;
+
+    /// Space to allocate for an account holding `counters` names of up to `MAX_NAME_LEN` bytes
+    pub const fn space(counters: usize) -> usize {
+        32 // authority
+            + 4 // counters length
+            + counters * (4 + Self::MAX_NAME_LEN + 8) // (name, value) pairs
+    }
+
+    /// Decode a multi-counter from raw account data, ignoring unused trailing space
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        Ok(Self::deserialize(&mut &data[..])?)
+    }
+
+    /// The value of the counter called `name`, if there is one
+    pub fn get(&self, name: &str) -> Option<u64> {
+        self.counters
+            .iter()
+            .find(|(counter_name, _)| counter_name == name)
+            .map(|(_, value)| *value)
+    }
+
+    /// Mutable access to the value of the counter called `name`
+    fn value_mut(&mut self, name: &str) -> Result<&mut u64, CounterError> {
+        self.counters
+            .iter_mut()
+            .find(|(counter_name, _)| counter_name == name)
+            .map(|(_, value)| value)
+            .ok_or_else(|| {
+                msg!("Error: No counter named {:?}", name);
+                CounterError::NotFound
+            })
+    }
+}
+
+/// Program-wide defaults for new counters, stored at the `CONFIG_SEED` PDA
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ConfigAccount {
+    /// The only account allowed to change the defaults
+    pub admin: Pubkey,
+    /// The `max_value` of counters created by a plain `Initialize`
+    pub default_max_value: u64,
+    /// The `cooldown_secs` of counters created by a plain `Initialize`
+    pub default_cooldown_secs: i64,
+}
+
+impl ConfigAccount {
+    /// Space to allocate for the config account
+    pub const LEN: usize = 32 // admin
+        + 8 // default_max_value
+        + 8; // default_cooldown_secs
+
+    /// The config PDA of `program_id` and its bump seed
+    pub fn address(program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[CONFIG_SEED], program_id)
+    }
+
+    /// The settings of a counter created with the program defaults
+    pub fn counter_config(&self) -> CounterConfig {
+        CounterConfig {
+            max_value: self.default_max_value,
+            cooldown_secs: self.default_cooldown_secs,
+            ..CounterConfig::default()
+        }
+    }
+}
+
+/// Settings chosen when a counter is created
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(BorshSchema))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CounterConfig {
+    /// The lowest value the counter may reach; the counter starts here
+    pub min_value: u64,
+    /// The highest value the counter may reach
+    pub max_value: u64,
+    /// The amount added or subtracted by `Increment` and `Decrement` (must be non-zero)
+    pub step: u64,
+    /// How out-of-range results are handled
+    pub mode: ArithmeticMode,
+    /// The count that triggers a callback to `milestone_target` when first reached
+    pub milestone: Option<u64>,
+    /// The program invoked when an increment crosses `milestone`
+    /// Must be set exactly when `milestone` is
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_base58"))]
+    pub milestone_target: Option<Pubkey>,
+    /// Allow at most one increment or decrement per slot
+    pub rate_limited: bool,
+    /// Seconds that must pass between changes to the count (0 for none, never negative)
+    pub cooldown_secs: i64,
+    /// The starting count, when not `min_value`; must lie within the limits
+    pub initial_value: Option<u64>,
+    /// Cycle to `min_value` when an increment passes `max_value`, and to
+    /// `max_value` when a decrement passes `min_value`, instead of failing
+    pub auto_reset: bool,
+    /// Human-readable name of at most `MAX_LABEL_LEN` bytes (empty for none)
+    pub label: String,
+    /// Generation reported in the `Initialized` event
+    pub nonce: u64,
+    /// Return the count to `min_value` on the first change in each new epoch
+    pub epoch_window: bool,
+    /// Position in the authority's family of indexed counters, set by `InitializeIndexed`
+    pub index: u32,
+    /// Amount the count leaks per second between changes, down to `min_value` (0 for none)
+    pub decay_per_sec: u64,
+    /// Reject changes to the count whose instruction carries any signer other than the
+    /// authority (or delegate) and multisig members
+    /// Hardens against confused clients, but breaks those that append further signing
+    /// accounts, such as a separate fee payer, to the counter's instructions; a fee payer
+    /// only in the transaction is unaffected.
+    pub strict_signers: bool,
+    /// Decimal places of a fixed-point count, for clients rendering it with `to_decimal`
+    /// At most `MAX_SCALE`.
+    pub scale: u32,
+    /// Unix timestamp from which the count can no longer change (0 for never)
+    pub expires_at: i64,
+}
+
+impl Default for CounterConfig {
+    fn default() -> Self {
+        Self {
+            min_value: 0,
+            max_value: u64::MAX,
+            step: 1,
+            mode: ArithmeticMode::Checked,
+            milestone: None,
+            milestone_target: None,
+            rate_limited: false,
+            cooldown_secs: 0,
+            initial_value: None,
+            auto_reset: false,
+            label: String::new(),
+            nonce: 0,
+            epoch_window: false,
+            index: 0,
+            decay_per_sec: 0,
+            strict_signers: false,
+            scale: 0,
+            expires_at: 0,
+        }
+    }
+}
+
+/// Instruction data sent to a counter's `milestone_target` when its milestone is crossed
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MilestoneReached {
+    /// The counter that crossed its milestone
+    pub counter: Pubkey,
+    /// The count after the crossing increment
+    pub count: u64,
+}
+
+/// Return data of a `Simulate` instruction
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub enum SimulationOutcome {
+    /// The instruction would succeed, leaving the counter at `count`
+    Succeeded {
+        /// The count the instruction would store
+        count: u64,
+    },
+    /// The instruction would fail
+    Failed {
+        /// The `ProgramError` it would fail with, as its numeric code
+        error: u64,
+    },
+}
+
+// Declare and export the program's entrypoint
+entrypoint!(process_instruction);
+
+/// Program entrypoint's implementation
+/// This is the main function that processes all instructions
+/// With the `debug-compute` feature, the remaining compute units are logged before and after.
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    #[cfg(feature = "debug-compute")]
+    solana_program::log::sol_log_compute_units();
+    
+    msg!("Simple Counter Program: Processing instruction");
+    
+    // Deserialize the instruction data
+    let result = CounterInstruction::unpack(instruction_data)
+        .and_then(|instruction| dispatch_instruction(program_id, accounts, instruction, false));
+    
+    #[cfg(feature = "debug-compute")]
+    solana_program::log::sol_log_compute_units();
+    result
+}
+
+/// Run the handler of a decoded instruction
+/// With `dry_run` set, a count change reports its outcome instead of storing it.
+fn dispatch_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction: CounterInstruction,
+    dry_run: bool,
+) -> ProgramResult {
+    match instruction {
+        CounterInstruction::Initialize => {
+            msg!("Instruction: Initialize");
+            let config = program_defaults(program_id, accounts)?;
+            initialize_counter(program_id, accounts, config, CounterKind::Unsigned, None)
+        }
+        CounterInstruction::Increment => {
+            msg!("Instruction: Increment");
+            if !dry_run && increment_in_place(program_id, accounts)? {
+                return Ok(());
+            }
+            increment_counter(program_id, accounts, None, dry_run)
+        }
+        CounterInstruction::Decrement => {
+            msg!("Instruction: Decrement");
+            decrement_counter(program_id, accounts, None, false, dry_run)
+        }
+        CounterInstruction::Reset => {
+            msg!("Instruction: Reset");
+            reset_counter(program_id, accounts, false, dry_run)
+        }
+        CounterInstruction::SetValue { value } => {
+            msg!("Instruction: SetValue");
+            set_counter_value(program_id, accounts, value, None, dry_run)
+        }
+        CounterInstruction::IncrementBy { amount } => {
+            msg!("Instruction: IncrementBy");
+            increment_counter(program_id, accounts, Some(amount), dry_run)
+        }
+        CounterInstruction::DecrementBy { amount } => {
+            msg!("Instruction: DecrementBy");
+            decrement_counter(program_id, accounts, Some(amount), false, dry_run)
+        }
+        CounterInstruction::TransferAuthority => {
+            msg!("Instruction: TransferAuthority");
+            transfer_authority(program_id, accounts)
+        }
+        CounterInstruction::ProposeAuthority { new_authority } => {
+            msg!("Instruction: ProposeAuthority");
+            propose_authority(program_id, accounts, new_authority)
+        }
+        CounterInstruction::AcceptAuthority => {
+            msg!("Instruction: AcceptAuthority");
+            accept_authority(program_id, accounts)
+        }
+        CounterInstruction::CloseCounter => {
+            msg!("Instruction: CloseCounter");
+            close_counter(program_id, accounts, None)
+        }
+        CounterInstruction::InitializeWithLimits { min_value, max_value } => {
+            msg!("Instruction: InitializeWithLimits");
+            let config = CounterConfig {
+                min_value,
+                max_value,
+                ..CounterConfig::default()
+            };
+            initialize_counter(program_id, accounts, config, CounterKind::Unsigned, None)
+        }
+        CounterInstruction::InitializeWithConfig { config } => {
+            msg!("Instruction: InitializeWithConfig");
+            initialize_counter(program_id, accounts, config, CounterKind::Unsigned, None)
+        }
+        CounterInstruction::InitializePda { seed } => {
+            msg!("Instruction: InitializePda");
+            initialize_pda_counter(program_id, accounts, seed.into_bytes(), CounterConfig::default())
+        }
+        CounterInstruction::SetMultisig { multisig } => {
+            msg!("Instruction: SetMultisig");
+            set_multisig(program_id, accounts, multisig)
+        }
+        CounterInstruction::SetDelegate { delegate } => {
+            msg!("Instruction: SetDelegate");
+            set_delegate(program_id, accounts, Some(delegate))
+        }
+        CounterInstruction::RevokeDelegate => {
+            msg!("Instruction: RevokeDelegate");
+            set_delegate(program_id, accounts, None)
+        }
+        CounterInstruction::InitializeSigned => {
+            msg!("Instruction: InitializeSigned");
+            initialize_counter(program_id, accounts, CounterConfig::default(), CounterKind::Signed, None)
+        }
+        CounterInstruction::Batch { ops } => {
+            msg!("Instruction: Batch");
+            apply_batch(program_id, accounts, &ops, dry_run)
+        }
+        CounterInstruction::InitializeMulti => {
+            msg!("Instruction: InitializeMulti");
+            initialize_multi_counter(program_id, accounts)
+        }
+        CounterInstruction::AddNamedCounter { name } => {
+            msg!("Instruction: AddNamedCounter");
+            add_named_counter(program_id, accounts, name)
+        }
+        CounterInstruction::IncrementNamed { name } => {
+            msg!("Instruction: IncrementNamed");
+            step_named_counter(program_id, accounts, &name, true)
+        }
+        CounterInstruction::DecrementNamed { name } => {
+            msg!("Instruction: DecrementNamed");
+            step_named_counter(program_id, accounts, &name, false)
+        }
+        CounterInstruction::Undo => {
+            msg!("Instruction: Undo");
+            undo_last_change(program_id, accounts, dry_run)
+        }
+        CounterInstruction::Freeze => {
+            msg!("Instruction: Freeze");
+            freeze_counter(program_id, accounts)
+        }
+        CounterInstruction::Pause => {
+            msg!("Instruction: Pause");
+            set_paused(program_id, accounts, true)
+        }
+        CounterInstruction::Unpause => {
+            msg!("Instruction: Unpause");
+            set_paused(program_id, accounts, false)
+        }
+        CounterInstruction::InitializeWithAuthority { authority } => {
+            msg!("Instruction: InitializeWithAuthority");
+            initialize_counter(
+                program_id,
+                accounts,
+                CounterConfig::default(),
+                CounterKind::Unsigned,
+                Some(authority),
+            )
+        }
+        CounterInstruction::InitializeWithValue { initial } => {
+            msg!("Instruction: InitializeWithValue");
+            let config = CounterConfig {
+                initial_value: Some(initial),
+                ..CounterConfig::default()
+            };
+            initialize_counter(program_id, accounts, config, CounterKind::Unsigned, None)
+        }
+        CounterInstruction::Migrate => {
+            msg!("Instruction: Migrate");
+            migrate_counter(program_id, accounts)
+        }
+        CounterInstruction::CloseCounterIfEquals { expected } => {
+            msg!("Instruction: CloseCounterIfEquals");
+            close_counter(program_id, accounts, Some(expected))
+        }
+        CounterInstruction::CompareAndSet { expected, new } => {
+            msg!("Instruction: CompareAndSet");
+            set_counter_value(program_id, accounts, new, Some(expected), dry_run)
+        }
+        CounterInstruction::InitConfig { default_max_value, default_cooldown_secs } => {
+            msg!("Instruction: InitConfig");
+            init_config(program_id, accounts, default_max_value, default_cooldown_secs)
+        }
+        CounterInstruction::UpdateConfig { default_max_value, default_cooldown_secs } => {
+            msg!("Instruction: UpdateConfig");
+            update_config(program_id, accounts, default_max_value, default_cooldown_secs)
+        }
+        CounterInstruction::AddAuthority { member } => {
+            msg!("Instruction: AddAuthority");
+            update_authorities(program_id, accounts, member, true)
+        }
+        CounterInstruction::RemoveAuthority { member } => {
+            msg!("Instruction: RemoveAuthority");
+            update_authorities(program_id, accounts, member, false)
+        }
+        CounterInstruction::DecrementToZero { amount } => {
+            msg!("Instruction: DecrementToZero");
+            decrement_counter(program_id, accounts, Some(amount), true, dry_run)
+        }
+        CounterInstruction::InitializeIfNeeded => {
+            msg!("Instruction: InitializeIfNeeded");
+            initialize_if_needed(program_id, accounts)
+        }
+        CounterInstruction::MultiplyBy { factor } => {
+            msg!("Instruction: MultiplyBy");
+            scale_counter(program_id, accounts, dry_run, |counter_data| counter_data.multiplied(factor))
+        }
+        CounterInstruction::DivideBy { divisor, round_up } => {
+            msg!("Instruction: DivideBy");
+            scale_counter(program_id, accounts, dry_run, |counter_data| counter_data.divided(divisor, round_up))
+        }
+        CounterInstruction::AssertCount { expected } => {
+            msg!("Instruction: AssertCount");
+            assert_count(program_id, accounts, expected)
+        }
+        CounterInstruction::TransferUnits { amount } => {
+            msg!("Instruction: TransferUnits");
+            transfer_units(program_id, accounts, amount)
+        }
+        CounterInstruction::InitializeWithLabel { label } => {
+            msg!("Instruction: InitializeWithLabel");
+            let config = CounterConfig {
+                label,
+                ..CounterConfig::default()
+            };
+            initialize_counter(program_id, accounts, config, CounterKind::Unsigned, None)
+        }
+        CounterInstruction::SetLabel { label } => {
+            msg!("Instruction: SetLabel");
+            set_label(program_id, accounts, label)
+        }
+        CounterInstruction::DoubleIncrement => {
+            msg!("Instruction: DoubleIncrement");
+            increment_counter(program_id, accounts, Some(2), dry_run)
+        }
+        CounterInstruction::InitializeMany { count } => {
+            msg!("Instruction: InitializeMany");
+            initialize_many(program_id, accounts, count)
+        }
+        CounterInstruction::InitializePdaWithNonce { seed, nonce } => {
+            msg!("Instruction: InitializePdaWithNonce");
+            let config = CounterConfig {
+                nonce,
+                ..CounterConfig::default()
+            };
+            initialize_pda_counter(program_id, accounts, seed.into_bytes(), config)
+        }
+        CounterInstruction::ResetToInitial => {
+            msg!("Instruction: ResetToInitial");
+            reset_counter(program_id, accounts, true, dry_run)
+        }
+        CounterInstruction::ReadMany => {
+            msg!("Instruction: ReadMany");
+            read_many(program_id, accounts)
+        }
+        CounterInstruction::InitializeIndexed { index } => {
+            msg!("Instruction: InitializeIndexed");
+            let config = CounterConfig {
+                index,
+                ..CounterConfig::default()
+            };
+            initialize_pda_counter(program_id, accounts, index.to_le_bytes().to_vec(), config)
+        }
+        CounterInstruction::IncrementAndReturn => {
+            msg!("Instruction: IncrementAndReturn");
+            increment_and_return(program_id, accounts)
+        }
+        CounterInstruction::WithdrawExcessLamports => {
+            msg!("Instruction: WithdrawExcessLamports");
+            withdraw_excess_lamports(program_id, accounts)
+        }
+        CounterInstruction::SnapshotTo { authority } => {
+            msg!("Instruction: SnapshotTo");
+            snapshot_counter(program_id, accounts, authority)
+        }
+        CounterInstruction::IncrementIf { threshold } => {
+            msg!("Instruction: IncrementIf");
+            increment_if(program_id, accounts, threshold)
+        }
+        CounterInstruction::DebugDump => {
+            msg!("Instruction: DebugDump");
+            debug_dump(program_id, accounts)
+        }
+        CounterInstruction::IncrementFixed { raw } => {
+            msg!("Instruction: IncrementFixed");
+            increment_counter(program_id, accounts, Some(raw), dry_run)
+        }
+        CounterInstruction::InitializeManyWithAuthorities { authorities } => {
+            msg!("Instruction: InitializeManyWithAuthorities");
+            initialize_many_with_authorities(program_id, accounts, authorities)
+        }
+        CounterInstruction::RepairHistory => {
+            msg!("Instruction: RepairHistory");
+            repair_history(program_id, accounts)
+        }
+        CounterInstruction::IncrementPercent { basis_points } => {
+            msg!("Instruction: IncrementPercent");
+            scale_counter(program_id, accounts, dry_run, |counter_data| counter_data.grown(basis_points))
+        }
+        CounterInstruction::Simulate { inner } => {
+            msg!("Instruction: Simulate");
+            simulate(program_id, accounts, *inner.0)
+        }
+        CounterInstruction::UpdateLimits { min_value, max_value } => {
+            msg!("Instruction: UpdateLimits");
+            update_limits(program_id, accounts, min_value, max_value)
+        }
+    }
+}
+
+/// Seeds of the PDA counter `authority` creates with `seed`, without the bump
+pub fn counter_seeds<'a>(authority: &'a Pubkey, seed: &'a [u8]) -> [&'a [u8]; 3] {
+    [COUNTER_SEED_PREFIX, authority.as_ref(), seed]
+}
+
+/// Seeds of generation `nonce` (little-endian) of the PDA counter `authority` creates
+/// with `seed`, without the bump
+/// Nonce 0 adds no seed, giving the `counter_seeds` of the first generation.
+pub fn counter_generation_seeds<'a>(authority: &'a Pubkey, seed: &'a [u8], nonce: &'a [u8; 8]) -> Vec<&'a [u8]> {
+    let mut seeds = counter_seeds(authority, seed).to_vec();
+    if *nonce != [0; 8] {
+        seeds.push(nonce);
+    }
+    seeds
+}
+
+/// Address and bump seed of the PDA counter `authority` creates with `InitializePda { seed }`
+pub fn counter_address(program_id: &Pubkey, authority: &Pubkey, seed: &str) -> (Pubkey, u8) {
+    counter_generation_address(program_id, authority, seed, 0)
+}
+
+/// Address and bump seed of the PDA counter `authority` creates with
+/// `InitializePdaWithNonce { seed, nonce }`
+pub fn counter_generation_address(program_id: &Pubkey, authority: &Pubkey, seed: &str, nonce: u64) -> (Pubkey, u8) {
+    let nonce = nonce.to_le_bytes();
+    Pubkey::find_program_address(&counter_generation_seeds(authority, seed.as_bytes(), &nonce), program_id)
+}
+
+/// Address and bump seed of the counter at `index` in `authority`'s family, created
+/// with `InitializeIndexed { index }`
+pub fn counter_address_for(program_id: &Pubkey, authority: &Pubkey, index: u32) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&counter_seeds(authority, &index.to_le_bytes()), program_id)
+}
+
+/// Decode raw counter account data, on-chain or off-chain
+/// Fails with `CounterError::NotInitialized` for a zero-filled account,
+/// `CounterError::InvalidAccountType` for an account without the counter discriminator,
+/// `CounterError::UnsupportedVersion` for a layout other than the current one and
+/// `ProgramError::InvalidAccountData` for data that does not decode as a counter
+pub fn decode_counter(data: &[u8]) -> Result<CounterAccount, ProgramError> {
+    if data.iter().all(|byte| *byte == 0) {
+        return Err(CounterError::NotInitialized.into());
+    }
+    if CounterAccount::stored_version(data)? != CounterAccount::VERSION {
+        return Err(CounterError::UnsupportedVersion.into());
+    }
+    CounterAccount::unpack(data).map_err(|_| ProgramError::InvalidAccountData)
+}
+
+/// Read the count of a fetched counter account
+#[cfg(feature = "client")]
+pub fn count_of(account: &solana_sdk::account::Account) -> Result<u64, ProgramError> {
+    Ok(decode_counter(&account.data)?.count.0)
+}
+
+/// Render a fixed-point `count` with `scale` decimal places, e.g. 150 at scale 2 as "1.50"
+/// A `scale` above `MAX_SCALE`, which no counter can hold, is treated as `MAX_SCALE`.
+pub fn to_decimal(count: u64, scale: u32) -> String {
+    let scale = scale.min(MAX_SCALE);
+    if scale == 0 {
+        return count.to_string();
+    }
+    let digits = format!("{:0>width$}", count, width = scale as usize + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - scale as usize);
+    format!("{}.{}", whole, fraction)
+}
+
+/// Read the count from raw counter account data, or `None` when the account is
+/// empty or does not hold a counter, so missing counters can be shown as zero
+pub fn try_read_count(data: &[u8]) -> Option<u64> {
+    decode_counter(data).ok().map(|counter_data| counter_data.count.0)
+}
+
+/// Read the count and authority of a counter account with a single decode
+pub fn read_counter(account: &AccountInfo) -> Result<(u64, Pubkey), ProgramError> {
+    let counter_data = decode_counter(&account.data.borrow())?;
+    Ok((counter_data.count.0, counter_data.authority))
+}
+
+/// Read the count of a counter owned by `program_id`, for programs that receive the
+/// counter account in their own instruction and must not trust its raw bytes
+/// Fails with `ProgramError::IncorrectProgramId` when another program owns the
+/// account, and otherwise like `decode_counter`. Needs no signer and changes nothing.
+pub fn get_count_checked(counter_account: &AccountInfo, program_id: &Pubkey) -> Result<u64, ProgramError> {
+    if counter_account.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    Ok(decode_counter(&counter_account.data.borrow())?.count.0)
+}
+
+/// Verify `signer` signed the transaction and is the `stored` authority
+/// The keys are compared in constant time. Every instruction checking an
+/// authority goes through here.
+pub fn verify_authority(stored: &Pubkey, signer: &AccountInfo) -> ProgramResult {
+    if !signer.is_signer {
+        msg!("Error: Authority must be a signer");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    let difference = stored
+        .as_ref()
+        .iter()
+        .zip(signer.key.as_ref())
+        .fold(0, |difference, (stored, signer)| difference | (stored ^ signer));
+    if difference != 0 {
+        msg!("Error: Authority mismatch");
+        return Err(CounterError::Unauthorized.into());
+    }
+    Ok(())
+}
+
+/// Verify a counter account is allocated at exactly `CounterAccount::LEN` bytes
+fn verify_counter_len(counter_account: &AccountInfo) -> ProgramResult {
+    let data_len = counter_account.data_len();
+    if data_len < CounterAccount::LEN {
+        msg!("Error: Counter account too small");
+        return Err(ProgramError::AccountDataTooSmall);
+    }
+    if data_len > CounterAccount::LEN {
+        msg!("Error: Counter account is {} bytes, expected {}", data_len, CounterAccount::LEN);
+        return Err(CounterError::InvalidAccountSize.into());
+    }
+    Ok(())
+}
+
+/// Verify a counter account is owned by the program, writable and initialized
+fn verify_counter_account(program_id: &Pubkey, counter_account: &AccountInfo) -> ProgramResult {
+    // Verify counter account is owned by our program
+    if counter_account.owner != program_id {
+        msg!("Error: Counter account not owned by program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    // Verify the changed counter can be stored back
+    if !counter_account.is_writable {
+        msg!("Error: Counter account must be writable");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    // Verify the account was initialized, rather than failing on a zeroed authority later
+    if counter_account.data_is_empty() || counter_account.data.borrow().iter().all(|byte| *byte == 0) {
+        msg!("Error: Counter account not initialized");
+        return Err(CounterError::NotInitialized.into());
+    }
+    Ok(())
+}
+
+/// Decode a counter account for modification, verifying its owner, that it is writable
+/// and initialized, its size, that a PDA counter lives at its derived address and that the counter has
+/// not been frozen
+fn load_counter(program_id: &Pubkey, counter_account: &AccountInfo) -> Result<CounterAccount, ProgramError> {
+    let counter_data = load_counter_even_if_frozen(program_id, counter_account)?;
+    if counter_data.frozen {
+        msg!("Error: Counter is frozen");
+        return Err(CounterError::Frozen.into());
+    }
+    Ok(counter_data)
+}
+
+/// Decode a counter account like `load_counter`, but accept a frozen counter
+/// Only for closing the counter and withdrawing its excess lamports, which never change the count.
+fn load_counter_even_if_frozen(program_id: &Pubkey, counter_account: &AccountInfo) -> Result<CounterAccount, ProgramError> {
+    verify_counter_account(program_id, counter_account)?;
+    verify_counter_len(counter_account)?;
+    let counter_data = decode_counter(&counter_account.data.borrow())?;
+    counter_data.verify_address(program_id, counter_account.key)?;
+    if counter_data.processing {
+        msg!("Error: Counter is in the middle of another operation");
+        return Err(CounterError::Reentrancy.into());
+    }
+    Ok(counter_data)
+}
+
+/// Verify the transaction carries the signatures needed to modify the count
+/// Without a multisig this is the authority set check; with one, `threshold`
+/// distinct members must sign among `authority` and the `extra_signers`.
+/// When `allow_delegate` is set and there is no multisig, the counter's delegate is
+/// accepted on its own.
+/// Fails first if `authority` did not sign or the counter is paused, since no
+/// signer may change it then. With `strict_signers` set, any other signer among the
+/// `extra_signers` fails too.
+fn verify_mutation_signers<'a>(
+    counter_data: &CounterAccount,
+    authority: &AccountInfo<'a>,
+    extra_signers: &[AccountInfo<'a>],
+    allow_delegate: bool,
+) -> ProgramResult {
+    if !authority.is_signer {
+        msg!("Error: Authority must be a signer");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if counter_data.paused {
+        msg!("Error: Counter is paused");
+        return Err(CounterError::Paused.into());
+    }
+    if counter_data.has_flag(CounterAccount::FLAG_STRICT_SIGNERS) {
+        verify_no_extra_signers(counter_data, authority.key, extra_signers)?;
+    }
+    
+    // A multisig binds the delegate too: it approves only by signing as a member
+    match &counter_data.multisig {
+        Some(multisig) => verify_multisig_approval(multisig, authority, extra_signers),
+        None if allow_delegate && counter_data.delegate == Some(*authority.key) => Ok(()),
+        None => verify_authority(counter_data.claimed_authority(authority.key), authority),
+    }
+}
+
+/// Verify at least `threshold` distinct multisig members signed, among `authority`
+/// and `extra_signers`
+fn verify_multisig_approval<'a>(
+    multisig: &MultisigConfig,
+    authority: &AccountInfo<'a>,
+    extra_signers: &[AccountInfo<'a>],
+) -> ProgramResult {
+    let mut approvals: Vec<&Pubkey> = Vec::with_capacity(multisig.signers.len());
+    for account in std::iter::once(authority).chain(extra_signers) {
+        if account.is_signer && multisig.signers.contains(account.key) && !approvals.contains(&account.key) {
+            approvals.push(account.key);
+        }
+    }
+    if approvals.len() < multisig.threshold as usize {
+        msg!("Error: {} of {} required multisig signatures", approvals.len(), multisig.threshold);
+        return Err(CounterError::NotEnoughSigners.into());
+    }
+    Ok(())
+}
+
+/// Verify every signer among `extra_signers` is `authority` or a multisig member
+fn verify_no_extra_signers(
+    counter_data: &CounterAccount,
+    authority: &Pubkey,
+    extra_signers: &[AccountInfo],
+) -> ProgramResult {
+    let expected = |key: &Pubkey| {
+        key == authority
+            || counter_data.multisig.as_ref().is_some_and(|multisig| multisig.signers.contains(key))
+    };
+    if let Some(account) = extra_signers.iter().find(|account| account.is_signer && !expected(account.key)) {
+        msg!("Error: Unexpected signer {}", account.key);
+        return Err(CounterError::UnexpectedSigner.into());
+    }
+    Ok(())
+}
+
+/// Record bookkeeping for an operation that modified `count`
+/// Fails once the counter has expired, and while its cooldown since the last change
+/// is still running.
+/// Clears the undo record; operations that can be undone set it afterwards.
+/// Releases the lock `begin_change` took, ending the operation.
+fn record_update(counter_data: &mut CounterAccount) -> ProgramResult {
+    let now = Clock::get()?.unix_timestamp;
+    if counter_data.has_flag(CounterAccount::FLAG_EXPIRY) && now >= counter_data.expires_at {
+        msg!("Error: Counter expired at {}", counter_data.expires_at);
+        return Err(CounterError::Expired.into());
+    }
+    if counter_data.has_flag(CounterAccount::FLAG_COOLDOWN) {
+        let ready_at = counter_data.last_updated.saturating_add(counter_data.cooldown_secs);
+        if now < ready_at {
+            msg!("Error: Counter cooling down until {}", ready_at);
+            return Err(CounterError::CooldownActive.into());
+        }
+    }
+    counter_data.last_updated = now;
+    counter_data.push_history();
+    counter_data.last_delta = 0;
+    counter_data.previous_count = None;
+    counter_data.processing = false;
+    counter_data.update_count = counter_data.update_count
+        .checked_add(1)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    Ok(())
+}
+
+/// Verify the signers may modify the count, apply the rate limit, start a new epoch
+/// window when one is due and take off accrued decay, which every change to the
+/// count starts with
+/// Locks the counter against re-entry until `record_update` ends the operation.
+fn begin_change<'a>(
+    counter_data: &mut CounterAccount,
+    authority: &AccountInfo<'a>,
+    extra_signers: &[AccountInfo<'a>],
+    allow_delegate: bool,
+) -> ProgramResult {
+    verify_mutation_signers(counter_data, authority, extra_signers, allow_delegate)?;
+    counter_data.processing = true;
+    enforce_rate_limit(counter_data)?;
+    roll_epoch_window(counter_data)?;
+    apply_decay(counter_data)
+}
+
+/// Reject a second rate limited change within the same slot, then record this one
+fn enforce_rate_limit(counter_data: &mut CounterAccount) -> ProgramResult {
+    if !counter_data.has_flag(CounterAccount::FLAG_RATE_LIMIT) {
+        return Ok(());
+    }
+    let current_slot = Clock::get()?.slot;
+    if current_slot == counter_data.last_slot {
+        msg!("Error: Counter already changed in slot {}", current_slot);
+        return Err(CounterError::RateLimited.into());
+    }
+    counter_data.last_slot = current_slot;
+    Ok(())
+}
+
+/// Start a new window for a counter with `epoch_window` set once the epoch has
+/// advanced since its window began, returning the count to `min_value`
+/// The undo record belongs to the old window, so it is dropped.
+fn roll_epoch_window(counter_data: &mut CounterAccount) -> ProgramResult {
+    if !counter_data.has_flag(CounterAccount::FLAG_EPOCH_WINDOW) {
+        return Ok(());
+    }
+    let epoch = Clock::get()?.epoch;
+    if epoch > counter_data.last_epoch {
+        msg!("Epoch {} started, resetting counter to {}", epoch, counter_data.min_value);
+        counter_data.count = counter_data.min_value;
+        counter_data.last_epoch = epoch;
+        counter_data.last_delta = 0;
+        counter_data.previous_count = None;
+    }
+    Ok(())
+}
+
+/// Take the decay accrued since the last change off the count, stopping at `min_value`
+fn apply_decay(counter_data: &mut CounterAccount) -> ProgramResult {
+    if !counter_data.has_flag(CounterAccount::FLAG_DECAY) {
+        return Ok(());
+    }
+    let elapsed = Clock::get()?.unix_timestamp.saturating_sub(counter_data.last_updated).max(0) as u64;
+    let decay = counter_data.decay_per_sec.saturating_mul(elapsed);
+    let decayed = CounterValue(counter_data.count.0.saturating_sub(decay)).max(counter_data.min_value);
+    if decayed < counter_data.count {
+        msg!("Counter decayed from {} to {} over {}s", counter_data.count, decayed, elapsed);
+        counter_data.count = decayed;
+    }
+    Ok(())
+}
+
+/// The settings of a plain `Initialize`: the program defaults when the config
+/// account is passed after the counter, the built-in defaults otherwise
+fn program_defaults(program_id: &Pubkey, accounts: &[AccountInfo]) -> Result<CounterConfig, ProgramError> {
+    match accounts.get(2) {
+        Some(config_account) => Ok(load_config(program_id, config_account)?.counter_config()),
+        None => Ok(CounterConfig::default()),
+    }
+}
+
+/// Initialize a new counter account
+/// The signing payer becomes the authority unless `authority` names another account
+fn initialize_counter(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    config: CounterConfig,
+    kind: CounterKind,
+    authority: Option<Pubkey>,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    
+    // Get accounts
+    let payer = next_account_info(account_iter)?;
+    let counter_account = next_account_info(account_iter)?;
+    
+    // Verify payer is signer
+    if !payer.is_signer {
+        msg!("Error: Authority must be a signer");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    let authority = authority.unwrap_or(*payer.key);
+    write_new_counter(program_id, &authority, counter_account, config, kind, 0, None)
+}
+
+/// Initialize a new counter for `authority` starting at the count of the source counter
+fn snapshot_counter(program_id: &Pubkey, accounts: &[AccountInfo], authority: Pubkey) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    
+    // Get accounts
+    let payer = next_account_info(account_iter)?;
+    let source_account = next_account_info(account_iter)?;
+    let counter_account = next_account_info(account_iter)?;
+    
+    // Verify payer is signer
+    if !payer.is_signer {
+        msg!("Error: Authority must be a signer");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    // Verify source account is owned by our program
+    if source_account.owner != program_id {
+        msg!("Error: Counter account not owned by program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    // Deserialize source counter data
+    let source_data = decode_counter(&source_account.data.borrow())?;
+    
+    let config = CounterConfig {
+        initial_value: Some(source_data.count.0),
+        ..CounterConfig::default()
+    };
+    write_new_counter(program_id, &authority, counter_account, config, source_data.kind, 0, None)?;
+    
+    msg!("Snapshot of {} taken at {}", source_account.key, source_data.count);
+    Ok(())
+}
+
+/// Initialize `count` counters with default settings in one instruction
+/// Any invalid account fails the instruction, so the runtime discards the
+/// counters already written.
+fn initialize_many(program_id: &Pubkey, accounts: &[AccountInfo], count: u8) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    
+    // Get accounts
+    let payer = next_account_info(account_iter)?;
+    
+    // Verify payer is signer
+    if !payer.is_signer {
+        msg!("Error: Authority must be a signer");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    for _ in 0..count {
+        let counter_account = next_account_info(account_iter)?;
+        write_new_counter(
+            program_id,
+            payer.key,
+            counter_account,
+            CounterConfig::default(),
+            CounterKind::Unsigned,
+            0,
+            None,
+        )?;
+    }
+    
+    msg!("Initialized {} counters", count);
+    Ok(())
+}
+
+/// Initialize one counter with default settings per authority in one instruction
+/// Like `initialize_many`, any invalid account fails the whole instruction.
+fn initialize_many_with_authorities(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    authorities: Vec<Pubkey>,
+) -> ProgramResult {
+    let (payer, counter_accounts) = accounts.split_first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    
+    // Verify payer is signer
+    if !payer.is_signer {
+        msg!("Error: Authority must be a signer");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    // Verify every counter account has exactly one authority
+    if counter_accounts.len() != authorities.len() {
+        msg!("Error: {} authorities for {} counter accounts", authorities.len(), counter_accounts.len());
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    for (counter_account, authority) in counter_accounts.iter().zip(&authorities) {
+        write_new_counter(
+            program_id,
+            authority,
+            counter_account,
+            CounterConfig::default(),
+            CounterKind::Unsigned,
+            0,
+            None,
+        )?;
+    }
+    
+    msg!("Initialized {} counters", authorities.len());
+    Ok(())
+}
+
+/// Initialize a counter unless the payer's counter is already stored in the account
+/// Accounts holding anything else, including undecodable data, are still rejected.
+fn initialize_if_needed(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    
+    // Get accounts
+    let payer = next_account_info(account_iter)?;
+    let counter_account = next_account_info(account_iter)?;
+    
+    if counter_account.owner == program_id {
+        match decode_counter(&counter_account.data.borrow()) {
+            Ok(counter_data) if counter_data.authority == *payer.key => {
+                msg!("Counter already initialized, nothing to do");
+                return Ok(());
+            }
+            Ok(_) => {
+                msg!("Error: Counter already initialized for another authority");
+                return Err(CounterError::AlreadyInitialized.into());
+            }
+            Err(err) if err == CounterError::NotInitialized.into() => {}
+            Err(err) => return Err(err),
+        }
+    }
+    
+    let config = program_defaults(program_id, accounts)?;
+    initialize_counter(program_id, accounts, config, CounterKind::Unsigned, None)
+}
+
+/// Create generation `config.nonce` of the counter at the PDA derived from the authority and `seed`
+fn initialize_pda_counter(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    seed: Vec<u8>,
+    config: CounterConfig,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    
+    // Get accounts
+    let authority = next_account_info(account_iter)?;
+    let counter_account = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+    
+    // Verify authority is signer
+    if !authority.is_signer {
+        msg!("Error: Authority must be a signer");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    
+    // Verify the seed fits in a single PDA seed
+    if seed.len() > MAX_SEED_LEN {
+        msg!("Error: Seed longer than {} bytes", MAX_SEED_LEN);
+        return Err(ProgramError::MaxSeedLengthExceeded);
+    }
+    
+    // Verify the passed account is the derived address
+    let nonce_bytes = config.nonce.to_le_bytes();
+    let mut signer_seeds = counter_generation_seeds(authority.key, &seed, &nonce_bytes);
+    let (expected_address, bump) = Pubkey::find_program_address(&signer_seeds, program_id);
+    if expected_address != *counter_account.key {
+        msg!("Error: Counter account does not match derived address {}", expected_address);
+        return Err(ProgramError::InvalidSeeds);
+    }
+    
+    // Create the account, signing for the PDA
+    let bump_seed = [bump];
+    signer_seeds.push(&bump_seed);
+    let account_len = CounterAccount::LEN;
+    let lamports = Rent::get()?.minimum_balance(account_len);
+    invoke_signed(
+        &system_instruction::create_account(
+            authority.key,
+            counter_account.key,
+            lamports,
+            account_len as u64,
+            program_id,
+        ),
+        &[authority.clone(), counter_account.clone(), system_program.clone()],
+        &[&signer_seeds],
+    )?;
+    
+    let pda_seeds = PdaSeeds {
+        authority: *authority.key,
+        seed,
+    };
+    write_new_counter(
+        program_id,
+        authority.key,
+        counter_account,
+        config,
+        CounterKind::Unsigned,
+        bump,
+        Some(pda_seeds),
+    )
+}
+
+/// Validate a freshly allocated counter account and write its initial state
+fn write_new_counter(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    counter_account: &AccountInfo,
+    config: CounterConfig,
+    kind: CounterKind,
+    bump: u8,
+    pda_seeds: Option<PdaSeeds>,
+) -> ProgramResult {
+    // Verify counter account is owned by our program
+    if counter_account.owner != program_id {
+        msg!("Error: Counter account not owned by program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    // Verify the authority can sign, or the counter could never be changed
+    if *authority == Pubkey::default() || *authority == system_program::id() {
+        msg!("Error: Authority {} can never sign", authority);
+        return Err(CounterError::InvalidAuthority.into());
+    }
+    
+    // Verify the limits describe a non-empty range
+    if config.min_value > config.max_value {
+        msg!("Error: Min value {} is above max value {}", config.min_value, config.max_value);
+        return Err(CounterError::InvalidLimits.into());
+    }
+    
+    // Verify the step actually moves the counter
+    if config.step == 0 {
+        msg!("Error: Step must be non-zero");
+        return Err(CounterError::InvalidStep.into());
+    }
+    
+    // Verify the cooldown is not negative
+    if config.cooldown_secs < 0 {
+        msg!("Error: Cooldown must not be negative");
+        return Err(CounterError::InvalidCooldown.into());
+    }
+    
+    // Verify the label fits the space reserved for it
+    verify_label(&config.label)?;
+    
+    // Verify the decimal places fit in the count
+    if config.scale > MAX_SCALE {
+        msg!("Error: Scale {} is above the maximum of {}", config.scale, MAX_SCALE);
+        return Err(CounterError::InvalidScale.into());
+    }
+    
+    // Verify a milestone always has a program to notify
+    if config.milestone.is_some() != config.milestone_target.is_some() {
+        msg!("Error: Milestone and milestone target must be set together");
+        return Err(CounterError::InvalidMilestone.into());
+    }
+    
+    // Check if account is already initialized (freshly allocated accounts are zero-filled)
+    if counter_account.data.borrow().iter().any(|byte| *byte != 0) {
+        msg!("Error: Counter account already initialized");
+        return Err(CounterError::AlreadyInitialized.into());
+    }
+    
+    // Verify account has exactly the counter's space
+    let account_len = CounterAccount::LEN;
+    verify_counter_len(counter_account)?;
+    
+    // Verify account is rent exempt
+    let rent = Rent::get()?;
+    if !rent.is_exempt(counter_account.lamports(), account_len) {
+        msg!("Error: Counter account not rent exempt");
+        return Err(ProgramError::AccountNotRentExempt);
+    }
+    
+    // Initialize the counter account
+    let initial_count = config.initial_value.unwrap_or(config.min_value);
+    let mut counter_data = CounterAccount {
+        discriminator: COUNTER_DISCRIMINATOR,
+        version: CounterAccount::VERSION,
+        count: CounterValue(initial_count),
+        authority: *authority,
+        last_updated: Clock::get()?.unix_timestamp,
+        update_count: 0,
+        min_value: CounterValue(config.min_value),
+        max_value: CounterValue(config.max_value),
+        step: config.step,
+        mode: config.mode,
+        kind,
+        bump,
+        milestone_fired: false,
+        rate_limited: config.rate_limited,
+        last_slot: 0,
+        cooldown_secs: config.cooldown_secs,
+        history: [CounterValue(0); HISTORY_LEN],
+        history_pos: 0,
+        last_delta: 0,
+        frozen: false,
+        paused: false,
+        processing: false,
+        flags: 0,
+        pending_authority: None,
+        pda_seeds,
+        multisig: None,
+        delegate: None,
+        milestone: config.milestone,
+        milestone_target: config.milestone_target,
+        previous_count: None,
+        initial_count: CounterValue(initial_count),
+        auto_reset: config.auto_reset,
+        authorities: Vec::new(),
+        label: config.label,
+        nonce: config.nonce,
+        epoch_window: config.epoch_window,
+        last_epoch: Clock::get()?.epoch,
+        index: config.index,
+        decay_per_sec: config.decay_per_sec,
+        strict_signers: config.strict_signers,
+        scale: config.scale,
+        expires_at: config.expires_at,
+    };
+    counter_data.flags = counter_data.feature_flags();
+    
+    // Verify the starting count respects the limits
+    counter_data.check_limits(counter_data.count)?;
+    
+    // Verify the account holds this counter as serialized, variable-length fields included
+    let serialized_len = counter_data.try_to_vec()?.len();
+    if counter_account.data_len() < serialized_len {
+        msg!("Error: Counter needs {} bytes, account has {}", serialized_len, counter_account.data_len());
+        return Err(ProgramError::AccountDataTooSmall);
+    }
+    
+    // Serialize and store data
+    counter_data.serialize(&mut &mut counter_account.data.borrow_mut()[..])?;
+    
+    CounterEvent::Initialized {
+        counter: *counter_account.key,
+        count: counter_data.count.0,
+        nonce: counter_data.nonce,
+    }
+    .emit()?;
+    
+    msg!("Counter initialized successfully with count: {}", counter_data.count);
+    Ok(())
+}
+
+/// Increment the counter by `amount`, or by its configured step when `None`
+/// With `dry_run` set, reports the new count instead of storing it.
+fn increment_counter(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: Option<u64>,
+    dry_run: bool,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    
+    // Get accounts
+    let authority = next_account_info(account_iter)?;
+    let counter_account = next_account_info(account_iter)?;
+    
+    // Deserialize counter account data
+    let mut counter_data = load_counter(program_id, counter_account)?;
+    
+    // Verify the signers may modify the count
+    begin_change(&mut counter_data, authority, account_iter.as_slice(), true)?;
+    
+    // Increment counter (with overflow protection)
+    let amount = amount.unwrap_or(counter_data.step);
+    let old_count = counter_data.count;
+    let cycled;
+    (counter_data.count, cycled) = counter_data.advanced(amount)?;
+    record_update(&mut counter_data)?;
+    // A cycle is not a plain step, so it can't be undone by stepping back
+    if !cycled {
+        counter_data.last_delta = counter_data.delta_since(old_count);
+    }
+    
+    // Note a first crossing of the milestone before storing, so it only fires once
+    let milestone_crossed = match counter_data.milestone {
+        Some(milestone) => {
+            !counter_data.milestone_fired && old_count < milestone && counter_data.count >= milestone
+        }
+        None => false,
+    };
+    if milestone_crossed {
+        counter_data.milestone_fired = true;
+        // Locked while the milestone target runs, so a callback can't change it
+        counter_data.processing = true;
+    }
+    
+    if dry_run {
+        return report_simulation(SimulationOutcome::Succeeded {
+            count: counter_data.count.0,
+        });
+    }
+    
+    // Serialize and store updated data
+    counter_data.serialize(&mut &mut counter_account.data.borrow_mut()[..])?;
+    
+    CounterEvent::Incremented {
+        counter: *counter_account.key,
+        count: counter_data.count.0,
+    }
+    .emit()?;
+    if cycled {
+        CounterEvent::Cycled {
+            counter: *counter_account.key,
+            count: counter_data.count.0,
+        }
+        .emit()?;
+    }
+    
+    if milestone_crossed {
+        notify_milestone(&counter_data, counter_account.key, account_iter.as_slice())?;
+        counter_data.processing = false;
+        counter_data.serialize(&mut &mut counter_account.data.borrow_mut()[..])?;
+    }
+    
+    msg!("Counter incremented to: {}", counter_data.count);
+    Ok(())
+}
+
+/// Increment a plain counter by its step directly in the account data, skipping Borsh
+/// Returns `false` without touching the account when the counter or the signer
+/// needs anything beyond the plain case, leaving it to `increment_counter`,
+/// which also reports any error.
+fn increment_in_place(program_id: &Pubkey, accounts: &[AccountInfo]) -> Result<bool, ProgramError> {
+    let account_iter = &mut accounts.iter();
+    
+    // Get accounts
+    let authority = next_account_info(account_iter)?;
+    let counter_account = next_account_info(account_iter)?;
+    
+    if counter_account.owner != program_id || !counter_account.is_writable {
+        return Ok(false);
+    }
+    
+    let mut data = counter_account.data.borrow_mut();
+    if !CounterData::is_plain(&data) {
+        return Ok(false);
+    }
+    let Some(counter) = CounterData::load_mut(&mut data) else {
+        return Ok(false);
+    };
+    
+    // Verify authority is signer and matches
+    if !authority.is_signer || counter.authority != *authority.key {
+        return Ok(false);
+    }
+    
+    // Increment counter (with overflow protection and limits)
+    let Some(new_count) = counter.count.checked_add(counter.step) else {
+        return Ok(false);
+    };
+    if new_count < { counter.min_value } || new_count > { counter.max_value } {
+        return Ok(false);
+    }
+    let Some(update_count) = counter.update_count.checked_add(1) else {
+        return Ok(false);
+    };
+    
+    // Same bookkeeping as `record_update`
+    let pos = counter.history_pos as usize % HISTORY_LEN;
+    counter.count = new_count;
+    counter.last_updated = Clock::get()?.unix_timestamp;
+    counter.history[pos] = new_count;
+    counter.history_pos = ((pos + 1) % HISTORY_LEN) as u8;
+    counter.last_delta = i64::try_from(counter.step).unwrap_or(0);
+    counter.update_count = update_count;
+    
+    CounterEvent::Incremented {
+        counter: *counter_account.key,
+        count: new_count.0,
+    }
+    .emit()?;
+    
+    msg!("Counter incremented in place to: {}", new_count);
+    Ok(true)
+}
+
+/// Invoke the counter's milestone target with a `MilestoneReached` payload
+/// The target program must be among `remaining_accounts`
+fn notify_milestone(
+    counter_data: &CounterAccount,
+    counter: &Pubkey,
+    remaining_accounts: &[AccountInfo],
+) -> ProgramResult {
+    let target = counter_data.milestone_target.ok_or(CounterError::InvalidMilestone)?;
+    let target_program = remaining_accounts
+        .iter()
+        .find(|account| *account.key == target)
+        .ok_or_else(|| {
+            msg!("Error: Milestone target program {} not passed", target);
+            ProgramError::NotEnoughAccountKeys
+        })?;
+    
+    let payload = MilestoneReached {
+        counter: *counter,
+        count: counter_data.count.0,
+    };
+    let instruction = Instruction::new_with_borsh(target, &payload, vec![]);
+    
+    msg!("Milestone reached, notifying {}", target);
+    invoke(&instruction, std::slice::from_ref(target_program))
+}
+
+/// Increment the counter by its step if the reference counter holds at least `threshold`
+fn increment_if(program_id: &Pubkey, accounts: &[AccountInfo], threshold: u64) -> ProgramResult {
+    let (reference_account, increment_accounts) =
+        accounts.split_first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    
+    // Verify the condition on the reference counter
+    let reference_count = get_count_checked(reference_account, program_id)?;
+    if reference_count < threshold {
+        msg!("Error: Reference counter is at {}, needs {}", reference_count, threshold);
+        return Err(CounterError::ConditionNotMet.into());
+    }
+    
+    increment_counter(program_id, increment_accounts, None, false)
+}
+
+/// Increment the counter by its step, then return the new count in the return data
+fn increment_and_return(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    increment_counter(program_id, accounts, None, false)?;
+    
+    // Read back the stored count, which increment_counter already validated
+    let counter_account = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let count = get_count_checked(counter_account, program_id)?;
+    set_return_data(&count.to_le_bytes());
+    Ok(())
+}
+
+/// Decrement the counter by `amount`, or by its configured step when `None`
+/// With `floor_at_zero` set, subtracts at most what takes the count to zero, or to
+/// `min_value` when that is higher.
+/// With `dry_run` set, reports the new count instead of storing it.
+fn decrement_counter(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: Option<u64>,
+    floor_at_zero: bool,
+    dry_run: bool,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    
+    // Get accounts
+    let authority = next_account_info(account_iter)?;
+    let counter_account = next_account_info(account_iter)?;
+    
+    // Deserialize counter account data
+    let mut counter_data = load_counter(program_id, counter_account)?;
+    
+    // Verify the signers may modify the count
+    begin_change(&mut counter_data, authority, account_iter.as_slice(), true)?;
+    
+    // Decrement counter (with underflow protection)
+    let mut amount = amount.unwrap_or(counter_data.step);
+    if floor_at_zero {
+        let available = match counter_data.kind {
+            CounterKind::Unsigned => counter_data.count.0.saturating_sub(counter_data.min_value.0),
+            CounterKind::Signed => counter_data.signed_count().max(0) as u64,
+        };
+        msg!("Subtracting {} of the requested {}", amount.min(available), amount);
+        amount = amount.min(available);
+    }
+    let old_count = counter_data.count;
+    let cycled;
+    (counter_data.count, cycled) = counter_data.retreated(amount)?;
+    record_update(&mut counter_data)?;
+    // A cycle is not a plain step, so it can't be undone by stepping back
+    if !cycled {
+        counter_data.last_delta = counter_data.delta_since(old_count);
+    }
+    
+    if dry_run {
+        return report_simulation(SimulationOutcome::Succeeded {
+            count: counter_data.count.0,
+        });
+    }
+    
+    // Serialize and store updated data
+    counter_data.serialize(&mut &mut counter_account.data.borrow_mut()[..])?;
+    
+    CounterEvent::Decremented {
+        counter: *counter_account.key,
+        count: counter_data.count.0,
+    }
+    .emit()?;
+    if cycled {
+        CounterEvent::Cycled {
+            counter: *counter_account.key,
+            count: counter_data.count.0,
+        }
+        .emit()?;
+    }
+    
+    msg!("Counter decremented to: {}", counter_data.count);
+    Ok(())
+}
+
+/// Reset the counter to zero, or with `to_initial` to the count it was initialized with
+/// With `dry_run` set, reports the new count instead of storing it.
+fn reset_counter(program_id: &Pubkey, accounts: &[AccountInfo], to_initial: bool, dry_run: bool) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    
+    // Get accounts
+    let authority = next_account_info(account_iter)?;
+    let counter_account = next_account_info(account_iter)?;
+    
+    // Deserialize counter account data
+    let mut counter_data = load_counter(program_id, counter_account)?;
+    let stored_count = counter_data.count;
+    
+    // Verify the signers may modify the count
+    begin_change(&mut counter_data, authority, account_iter.as_slice(), false)?;
+    
+    // Enforce the configured limits
+    let target = if to_initial { counter_data.initial_count } else { CounterValue(0) };
+    counter_data.check_limits(target)?;
+    
+    // Reset counter
+    counter_data.count = target;
+    record_update(&mut counter_data)?;
+    
+    if dry_run {
+        return report_simulation(SimulationOutcome::Succeeded {
+            count: counter_data.count.0,
+        });
+    }
+    
+    // Serialize and store updated data
+    counter_data.serialize(&mut &mut counter_account.data.borrow_mut()[..])?;
+    
+    CounterEvent::CountChanged {
+        counter: *counter_account.key,
+        old: stored_count.0,
+        new: counter_data.count.0,
+    }
+    .emit()?;
+    
+    msg!("Counter reset from {} to {}", stored_count, counter_data.count);
+    Ok(())
+}
+
+/// Set the counter to a specific value
+/// With `expected` set, the count must equal it or nothing is written
+/// With `dry_run` set, reports the new count instead of storing it.
+fn set_counter_value(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    value: u64,
+    expected: Option<u64>,
+    dry_run: bool,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    
+    // Get accounts
+    let authority = next_account_info(account_iter)?;
+    let counter_account = next_account_info(account_iter)?;
+    
+    // Deserialize counter account data
+    let mut counter_data = load_counter(program_id, counter_account)?;
+    let stored_count = counter_data.count;
+    
+    // Verify the signers may modify the count
+    begin_change(&mut counter_data, authority, account_iter.as_slice(), false)?;
+    
+    // Verify the count matches, when the caller asked to compare before setting
+    if let Some(expected) = expected {
+        if counter_data.count != expected {
+            msg!("Error: Counter is at {}, expected {}", counter_data.count, expected);
+            return Err(CounterError::Conflict.into());
+        }
+    }
+    
+    // Enforce the configured limits
+    counter_data.check_limits(CounterValue(value))?;
+    
+    // Store the requested value
+    let old_count = counter_data.count;
+    counter_data.count = CounterValue(value);
+    record_update(&mut counter_data)?;
+    counter_data.previous_count = Some(old_count);
+    
+    if dry_run {
+        return report_simulation(SimulationOutcome::Succeeded {
+            count: counter_data.count.0,
+        });
+    }
+    
+    // Serialize and store updated data
+    counter_data.serialize(&mut &mut counter_account.data.borrow_mut()[..])?;
+    
+    CounterEvent::CountChanged {
+        counter: *counter_account.key,
+        old: stored_count.0,
+        new: counter_data.count.0,
+    }
+    .emit()?;
+    
+    msg!("Counter set to: {}", counter_data.count);
+    Ok(())
+}
+
+/// Replace the count with the result of `scale`, checked like an increment
+/// With `dry_run` set, reports the new count instead of storing it.
+fn scale_counter(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    dry_run: bool,
+    scale: impl FnOnce(&CounterAccount) -> Result<CounterValue, CounterError>,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    
+    // Get accounts
+    let authority = next_account_info(account_iter)?;
+    let counter_account = next_account_info(account_iter)?;
+    
+    // Deserialize counter account data
+    let mut counter_data = load_counter(program_id, counter_account)?;
+    let stored_count = counter_data.count;
+    
+    // Verify the signers may modify the count
+    begin_change(&mut counter_data, authority, account_iter.as_slice(), true)?;
+    
+    // Scale the count, keeping the old one for `Undo`
+    let old_count = counter_data.count;
+    counter_data.count = scale(&counter_data)?;
+    record_update(&mut counter_data)?;
+    counter_data.previous_count = Some(old_count);
+    
+    if dry_run {
+        return report_simulation(SimulationOutcome::Succeeded {
+            count: counter_data.count.0,
+        });
+    }
+    
+    // Serialize and store updated data
+    counter_data.serialize(&mut &mut counter_account.data.borrow_mut()[..])?;
+    
+    CounterEvent::CountChanged {
+        counter: *counter_account.key,
+        old: stored_count.0,
+        new: counter_data.count.0,
+    }
+    .emit()?;
+    
+    msg!("Counter scaled from {} to {}", old_count, counter_data.count);
+    Ok(())
+}
+
+/// Move `amount` units from the source counter to the destination counter
+fn transfer_units(program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    
+    // Get accounts
+    let authority = next_account_info(account_iter)?;
+    let source_account = next_account_info(account_iter)?;
+    let destination_account = next_account_info(account_iter)?;
+    
+    // Moving units within one counter would write the source's change over itself
+    if source_account.key == destination_account.key {
+        msg!("Error: Source and destination must be different counters");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    // Deserialize counter account data
+    let mut source_data = load_counter(program_id, source_account)?;
+    let mut destination_data = load_counter(program_id, destination_account)?;
+    if source_data.kind != CounterKind::Unsigned || destination_data.kind != CounterKind::Unsigned {
+        msg!("Error: Units can only move between unsigned counters");
+        return Err(ProgramError::InvalidArgument);
+    }
+    
+    // Verify the signers may modify both counts
+    for counter_data in [&mut source_data, &mut destination_data] {
+        begin_change(counter_data, authority, account_iter.as_slice(), false)?;
+    }
+    
+    // Move the units (with underflow and overflow protection)
+    source_data.count = source_data.count.checked_sub(amount).ok_or(CounterError::Underflow)?;
+    source_data.check_limits(source_data.count)?;
+    destination_data.count = destination_data.count.checked_add(amount).ok_or(CounterError::Overflow)?;
+    destination_data.check_limits(destination_data.count)?;
+    record_update(&mut source_data)?;
+    record_update(&mut destination_data)?;
+    
+    // Serialize and store updated data
+    source_data.serialize(&mut &mut source_account.data.borrow_mut()[..])?;
+    destination_data.serialize(&mut &mut destination_account.data.borrow_mut()[..])?;
+    
+    CounterEvent::Decremented {
+        counter: *source_account.key,
+        count: source_data.count.0,
+    }
+    .emit()?;
+    CounterEvent::Incremented {
+        counter: *destination_account.key,
+        count: destination_data.count.0,
+    }
+    .emit()?;
+    
+    msg!("Moved {} units from {} to {}", amount, source_account.key, destination_account.key);
+    Ok(())
+}
+
+/// Verify the counter holds `expected`
+fn assert_count(program_id: &Pubkey, accounts: &[AccountInfo], expected: u64) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    
+    // Get accounts
+    let counter_account = next_account_info(account_iter)?;
+    
+    // Verify counter account is owned by our program
+    if counter_account.owner != program_id {
+        msg!("Error: Counter account not owned by program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    // Deserialize counter account data
+    let counter_data = decode_counter(&counter_account.data.borrow())?;
+    
+    if counter_data.count != expected {
+        msg!("Error: Counter is at {}, expected {}", counter_data.count, expected);
+        return Err(CounterError::AssertionFailed.into());
+    }
+    
+    msg!("Counter is at {} as expected", expected);
+    Ok(())
+}
+
+/// Log the full decoded state of the counter
+fn debug_dump(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    
+    // Get accounts
+    let counter_account = next_account_info(account_iter)?;
+    
+    // Verify counter account is owned by our program
+    if counter_account.owner != program_id {
+        msg!("Error: Counter account not owned by program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    
+    // Deserialize counter account data
+    let counter_data = decode_counter(&counter_account.data.borrow())?;
+    
+    msg!("Counter {}: {:?}", counter_account.key, counter_data);
+    Ok(())
+}
+
+/// Write the count of every counter account into the return data
+fn read_many(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    // Verify the counts fit in the return data
+    if accounts.len() > MAX_RETURN_DATA / 8 {
+        msg!("Error: {} counters requested, at most {} fit", accounts.len(), MAX_RETURN_DATA / 8);
+        return Err(CounterError::TooLarge.into());
+    }
+    
+    let mut counts = Vec::with_capacity(accounts.len() * 8);
+    for counter_account in accounts {
+        counts.extend_from_slice(&get_count_checked(counter_account, program_id)?.to_le_bytes());
+    }
+    set_return_data(&counts);
+    
+    msg!("Read {} counters", accounts.len());
+    Ok(())
+}
+
+/// Revert the counter's last undoable change
+/// With `dry_run` set, reports the new count instead of storing it.
+fn undo_last_change(program_id: &Pubkey, accounts: &[AccountInfo], dry_run: bool) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    
+    // Get accounts
+    let authority = next_account_info(account_iter)?;
+    let counter_account = next_account_info(account_iter)?;
+    
+    // Deserialize counter account data
+    let mut counter_data = load_counter(program_id, counter_account)?;
+    let stored_count = counter_data.count;
+    
+    // Verify the signers may modify the count
+    begin_change(&mut counter_data, authority, account_iter.as_slice(), false)?;
+    
+    // Restore the prior value, or apply the inverse of the last delta
+    counter_data.count = match (counter_data.previous_count, counter_data.last_delta) {
+        (Some(previous_count), _) => {
+            counter_data.check_limits(previous_count)?;
+            previous_count
+        }
+        (None, 0) => {
+            msg!("Error: No change to undo");
+            return Err(CounterError::NothingToUndo.into());
+        }
+        (None, delta) if delta > 0 => counter_data.subtracted(delta.unsigned_abs())?,
+        (None, delta) => counter_data.added(delta.unsigned_abs())?,
+    };
+    
+    // Clears the undo record so undo can't be chained
+    record_update(&mut counter_data)?;
+    
+    if dry_run {
+        return report_simulation(SimulationOutcome::Succeeded {
+            count: counter_data.count.0,
+        });
+    }
+    
+    // Serialize and store updated data
+    counter_data.serialize(&mut &mut counter_account.data.borrow_mut()[..])?;
+    
+    CounterEvent::CountChanged {
+        counter: *counter_account.key,
+        old: stored_count.0,
+        new: counter_data.count.0,
+    }
+    .emit()?;
+    
+    msg!("Counter restored from {} to {}", stored_count, counter_data.count);
+    Ok(())
+}
+
+/// Apply each delta in `ops` in turn, failing the whole batch on the first bad step
+/// With `dry_run` set, reports the new count instead of storing it.
+fn apply_batch(program_id: &Pubkey, accounts: &[AccountInfo], ops: &[i64], dry_run: bool) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    
+    // Get accounts
+    let authority = next_account_info(account_iter)?;
+    let counter_account = next_account_info(account_iter)?;
+    
+    // Verify the batch stays within the per-instruction cap
+    if ops.len() > MAX_BATCH_OPS {
+        msg!("Error: Batch has {} ops, at most {} allowed", ops.len(), MAX_BATCH_OPS);
+        return Err(CounterError::TooLarge.into());
+    }
+    
+    // Deserialize counter account data
+    let mut counter_data = load_counter(program_id, counter_account)?;
+    let stored_count = counter_data.count;
+    
+    // Verify the signers may modify the count
+    begin_change(&mut counter_data, authority, account_iter.as_slice(), true)?;
+    
+    // Fold every delta into the count (with overflow and underflow protection)
+    for (index, delta) in ops.iter().enumerate() {
+        counter_data.count = if *delta >= 0 {
+            counter_data.added(delta.unsigned_abs())
+        } else {
+            counter_data.subtracted(delta.unsigned_abs())
+        }
+        .inspect_err(|_| msg!("Error: Batch step {} ({}) failed", index, delta))?;
+    }
+    record_update(&mut counter_data)?;
+    
+    if dry_run {
+        return report_simulation(SimulationOutcome::Succeeded {
+            count: counter_data.count.0,
+        });
+    }
+    
+    // Serialize and store updated data
+    counter_data.serialize(&mut &mut counter_account.data.borrow_mut()[..])?;
+    
+    CounterEvent::CountChanged {
+        counter: *counter_account.key,
+        old: stored_count.0,
+        new: counter_data.count.0,
+    }
+    .emit()?;
+    
+    msg!("Counter moved from {} to {} by {} ops", stored_count, counter_data.count, ops.len());
+    Ok(())
+}
+
+/// Transfer control of the counter to a new authority
+fn transfer_authority(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    
+    // Get accounts
+    let authority = next_account_info(account_iter)?;
+    let counter_account = next_account_info(account_iter)?;
+    let new_authority = next_account_info(account_iter)?;
+    
+    // Deserialize counter account data
+    let mut counter_data = load_counter(program_id, counter_account)?;
+    
+    // Verify authority is signer and matches
+    verify_authority(&counter_data.authority, authority)?;
+    
+    // Hand over control, dropping any outstanding proposal
+    let old_authority = counter_data.authority;
+    counter_data.authority = *new_authority.key;
+    counter_data.pending_authority = None;
+    
+    // Serialize and store updated data
+    counter_data.serialize(&mut &mut counter_account.data.borrow_mut()[..])?;
+    
+    CounterEvent::AuthorityChanged {
+        counter: *counter_account.key,
+        old: old_authority,
+        new: counter_data.authority,
+    }
+    .emit()?;
+    
+    msg!("Counter authority transferred to: {}", counter_data.authority);
+    Ok(())
+}
+
+/// Propose (or clear) the next authority of the counter
+fn propose_authority(program_id: &Pubkey, accounts: &[AccountInfo], new_authority: Option<Pubkey>) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    
+    // Get accounts
+    let authority = next_account_info(account_iter)?;
+    let counter_account = next_account_info(account_iter)?;
+    
+    // Deserialize counter account data
+    let mut counter_data = load_counter(program_id, counter_account)?;
+    
+    // Verify authority is signer and matches
+    verify_authority(&counter_data.authority, authority)?;
+    
+    // Record the proposal, replacing any previous one
+    counter_data.pending_authority = new_authority;
+    
+    // Serialize and store updated data
+    counter_data.serialize(&mut &mut counter_account.data.borrow_mut()[..])?;
+    
+    match counter_data.pending_authority {
+        Some(pending) => msg!("Counter authority proposed: {}", pending),
+        None => msg!("Counter authority proposal cleared"),
+    }
+    Ok(())
+}
+
+/// Accept a pending authority proposal
+fn accept_authority(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    
+    // Get accounts
+    let new_authority = next_account_info(account_iter)?;
+    let counter_account = next_account_info(account_iter)?;
+    
+    // Deserialize counter account data
+    let mut counter_data = load_counter(program_id, counter_account)?;
+    
+    // Verify the signer is the pending authority
+    let pending_authority = counter_data.pending_authority.ok_or_else(|| {
+        msg!("Error: No authority transfer pending");
+        CounterError::Unauthorized
+    })?;
+    verify_authority(&pending_authority, new_authority)?;
+    
+    // Finalize the transfer
+    let old_authority = counter_data.authority;
+    counter_data.authority = *new_authority.key;
+    counter_data.pending_authority = None;
+    
+    // Serialize and store updated data
+    counter_data.serialize(&mut &mut counter_account.data.borrow_mut()[..])?;
+    
+    CounterEvent::AuthorityChanged {
+        counter: *counter_account.key,
+        old: old_authority,
+        new: counter_data.authority,
+    }
+    .emit()?;
+    
+    msg!("Counter authority accepted by: {}", counter_data.authority);
+    Ok(())
+}
+
+/// Close the counter, sending its lamports to a destination account
+/// With `expected` set, the count must equal it or nothing is closed
+fn close_counter(program_id: &Pubkey, accounts: &[AccountInfo], expected: Option<u64>) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    
+    // Get accounts
+    let authority = next_account_info(account_iter)?;
+    let counter_account = next_account_info(account_iter)?;
+    let destination = next_account_info(account_iter)?;
+    
+    // Deserialize counter account data; a frozen counter can still be closed
+    let counter_data = load_counter_even_if_frozen(program_id, counter_account)?;
+    
+    // Verify authority is signer and matches
+    verify_authority(&counter_data.authority, authority)?;
+    
+    // Verify the count matches, when the caller asked to compare before closing
+    if let Some(expected) = expected {
+        if counter_data.count != expected {
+            msg!("Error: Counter is at {}, expected {}", counter_data.count, expected);
+            return Err(CounterError::Conflict.into());
+        }
+    }
+    
+    // Move all lamports out so the runtime garbage-collects the account
+    let reclaimed = counter_account.lamports();
+    **destination.lamports.borrow_mut() = destination
+        .lamports()
+        .checked_add(reclaimed)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    **counter_account.lamports.borrow_mut() = 0;
+    
+    // Wipe the state so the account can't be mistaken for a live counter
+    counter_account.data.borrow_mut().fill(0);
+    
+    msg!("Counter closed, {} lamports sent to {}", reclaimed, destination.key);
+    Ok(())
+}
+
+/// Send the counter's lamports above its rent-exempt minimum to the destination
+fn withdraw_excess_lamports(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    
+    // Get accounts
+    let authority = next_account_info(account_iter)?;
+    let counter_account = next_account_info(account_iter)?;
+    let destination = next_account_info(account_iter)?;
+    
+    // Deserialize counter account data
+    let counter_data = load_counter_even_if_frozen(program_id, counter_account)?;
+    
+    // Verify authority is signer and matches
+    verify_authority(&counter_data.authority, authority)?;
+    
+    // Move only the surplus, so the counter stays rent-exempt
+    let minimum = Rent::get()?.minimum_balance(counter_account.data_len());
+    let excess = counter_account.lamports().saturating_sub(minimum);
+    **destination.lamports.borrow_mut() = destination
+        .lamports()
+        .checked_add(excess)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    **counter_account.lamports.borrow_mut() -= excess;
+    
+    msg!("Withdrew {} excess lamports to {}", excess, destination.key);
+    Ok(())
+}
+
+/// Require (or stop requiring) a multisig for count changes
+fn set_multisig(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    multisig: Option<MultisigConfig>,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    
+    // Get accounts
+    let authority = next_account_info(account_iter)?;
+    let counter_account = next_account_info(account_iter)?;
+    
+    // Deserialize counter account data
+    let mut counter_data = load_counter(program_id, counter_account)?;
+    
+    // Verify authority is signer and matches, and that the current multisig approves
+    verify_authority(&counter_data.authority, authority)?;
+    if let Some(current) = &counter_data.multisig {
+        verify_multisig_approval(current, authority, account_iter.as_slice())?;
+    }
+    
+    // Verify the multisig is satisfiable and within the account's space
+    if let Some(multisig) = &multisig {
+        let members = multisig.signers.len();
+        let has_duplicates = multisig
+            .signers
+            .iter()
+            .enumerate()
+            .any(|(i, signer)| multisig.signers[..i].contains(signer));
+        if members > MAX_MULTISIG_SIGNERS
+            || multisig.threshold == 0
+            || multisig.threshold as usize > members
+            || has_duplicates
+        {
+            msg!("Error: Invalid {}-of-{} multisig", multisig.threshold, members);
+            return Err(CounterError::InvalidMultisig.into());
+        }
+    }
+    
+    counter_data.multisig = multisig;
+    
+    // Serialize and store updated data
+    counter_data.serialize(&mut &mut counter_account.data.borrow_mut()[..])?;
+    
+    match &counter_data.multisig {
+        Some(multisig) => msg!(
+            "Counter now requires {}-of-{} multisig",
+            multisig.threshold,
+            multisig.signers.len()
+        ),
+        None => msg!("Counter multisig removed"),
+    }
+    Ok(())
+}
+
+/// Set or clear the counter's delegate
+fn set_delegate(program_id: &Pubkey, accounts: &[AccountInfo], delegate: Option<Pubkey>) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    
+    // Get accounts
+    let authority = next_account_info(account_iter)?;
+    let counter_account = next_account_info(account_iter)?;
+    
+    // Deserialize counter account data
+    let mut counter_data = load_counter(program_id, counter_account)?;
+    
+    // Verify authority is signer and matches, and that any multisig approves
+    verify_authority(&counter_data.authority, authority)?;
+    if let Some(multisig) = &counter_data.multisig {
+        verify_multisig_approval(multisig, authority, account_iter.as_slice())?;
+    }
+    
+    counter_data.delegate = delegate;
+    
+    // Serialize and store updated data
+    counter_data.serialize(&mut &mut counter_account.data.borrow_mut()[..])?;
+    
+    match counter_data.delegate {
+        Some(delegate) => msg!("Counter delegate set to: {}", delegate),
+        None => msg!("Counter delegate revoked"),
+    }
+    Ok(())
+}
+
+/// Reject labels longer than `MAX_LABEL_LEN` bytes
+fn verify_label(label: &str) -> ProgramResult {
+    if label.len() > MAX_LABEL_LEN {
+        msg!("Error: Label is {} bytes, at most {} allowed", label.len(), MAX_LABEL_LEN);
+        return Err(CounterError::LabelTooLong.into());
+    }
+    Ok(())
 }
 
-/// Define program instructions
-/// This is like API endpoints in traditional apps
-#[derive(BorshSerialize, BorshDeserialize, Debug)]
-pub enum CounterInstruction {
-    /// Initialize a new counter account
-    /// Accounts expected:
-    /// 0. `[signer]` The account that will pay for the account creation
-    /// 1. `[writable]` The counter account to create
-    Initialize,
-    
-    /// Increment the counter
-    /// Accounts expected:
-    /// 0. `[signer]` The authority account
-    /// 1. `[writable]` The counter account to increment
-    Increment,
-    
-    /// Decrement the counter  
-    /// Accounts expected:
-    /// 0. `[signer]` The authority account
-    /// 1. `[writable]` The counter account to decrement
-    Decrement,
+/// Rename the counter
+fn set_label(program_id: &Pubkey, accounts: &[AccountInfo], label: String) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    
+    // Get accounts
+    let authority = next_account_info(account_iter)?;
+    let counter_account = next_account_info(account_iter)?;
+    
+    // Deserialize counter account data
+    let mut counter_data = load_counter(program_id, counter_account)?;
+    
+    // Verify authority is signer and matches
+    verify_authority(&counter_data.authority, authority)?;
+    
+    verify_label(&label)?;
+    counter_data.label = label;
+    
+    // Serialize and store updated data
+    counter_data.serialize(&mut &mut counter_account.data.borrow_mut()[..])?;
+    
+    msg!("Counter label set to: {:?}", counter_data.label);
+    Ok(())
 }
 
-// Declare and export the program's entrypoint
-entrypoint!(process_instruction);
+/// Replace the counter's limits with `min_value..=max_value`
+fn update_limits(program_id: &Pubkey, accounts: &[AccountInfo], min_value: u64, max_value: u64) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    
+    // Get accounts
+    let authority = next_account_info(account_iter)?;
+    let counter_account = next_account_info(account_iter)?;
+    
+    // Deserialize counter account data
+    let mut counter_data = load_counter(program_id, counter_account)?;
+    
+    // Verify authority is signer and matches
+    verify_authority(&counter_data.authority, authority)?;
+    
+    // Signed counters do not enforce limits, so new ones would have no effect
+    if counter_data.kind == CounterKind::Signed {
+        msg!("Error: Signed counters have no limits to update");
+        return Err(CounterError::UnsupportedKind.into());
+    }
+    
+    // Verify the limits describe a non-empty range
+    if min_value > max_value {
+        msg!("Error: Min value {} is above max value {}", min_value, max_value);
+        return Err(CounterError::InvalidLimits.into());
+    }
+    
+    // Verify the current count stays within the new limits
+    if !(min_value..=max_value).contains(&counter_data.count.0) {
+        msg!("Error: Count {} is outside {}..={}", counter_data.count, min_value, max_value);
+        return Err(CounterError::ValueOutOfRange.into());
+    }
+    counter_data.min_value = CounterValue(min_value);
+    counter_data.max_value = CounterValue(max_value);
+    
+    // Serialize and store updated data
+    counter_data.serialize(&mut &mut counter_account.data.borrow_mut()[..])?;
+    
+    msg!("Counter limits set to {}..={}", min_value, max_value);
+    Ok(())
+}
 
-/// Program entrypoint's implementation
-/// This is the main function that processes all instructions
-pub fn process_instruction(
-    program_id: &Pubkey,
-    accounts: &[AccountInfo],
-    instruction_data: &[u8],
-) -> ProgramResult {
-    msg!("Simple Counter Program: Processing instruction");
+/// Run `inner` against the counter without storing, emitting or invoking anything,
+/// and report the outcome in the return data
+fn simulate(program_id: &Pubkey, accounts: &[AccountInfo], inner: CounterInstruction) -> ProgramResult {
+    // Verify the instruction is a count change, whose handler can stop short of storing it
+    let simulatable = matches!(
+        inner,
+        CounterInstruction::Increment
+            | CounterInstruction::Decrement
+            | CounterInstruction::Reset
+            | CounterInstruction::SetValue { .. }
+            | CounterInstruction::IncrementBy { .. }
+            | CounterInstruction::DecrementBy { .. }
+            | CounterInstruction::Batch { .. }
+            | CounterInstruction::Undo
+            | CounterInstruction::CompareAndSet { .. }
+            | CounterInstruction::DecrementToZero { .. }
+            | CounterInstruction::MultiplyBy { .. }
+            | CounterInstruction::DivideBy { .. }
+            | CounterInstruction::DoubleIncrement
+            | CounterInstruction::ResetToInitial
+            | CounterInstruction::IncrementFixed { .. }
+            | CounterInstruction::IncrementPercent { .. }
+    );
+    if !simulatable {
+        msg!("Error: Only count changes to a single counter can be simulated");
+        return Err(ProgramError::InvalidInstructionData);
+    }
     
-    // Deserialize the instruction data
-    let instruction = CounterInstruction::try_from_slice(instruction_data)
-        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    // A successful dry run reports its own outcome
+    if let Err(err) = dispatch_instruction(program_id, accounts, inner, true) {
+        report_simulation(SimulationOutcome::Failed { error: err.into() })?;
+    }
+    Ok(())
+}
+
+/// Report the outcome of a simulated change in the return data
+fn report_simulation(outcome: SimulationOutcome) -> ProgramResult {
+    msg!("Simulated: {:?}", outcome);
+    set_return_data(&outcome.try_to_vec()?);
+    Ok(())
+}
+
+/// Sanitize the counter's history buffer, logging each fix
+fn repair_history(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
     
-    match instruction {
-        CounterInstruction::Initialize => {
-            msg!("Instruction: Initialize");
-            initialize_counter(program_id, accounts)
+    // Get accounts
+    let authority = next_account_info(account_iter)?;
+    let counter_account = next_account_info(account_iter)?;
+    
+    // Deserialize counter account data
+    let mut counter_data = load_counter(program_id, counter_account)?;
+    
+    // Verify authority is signer and matches
+    verify_authority(&counter_data.authority, authority)?;
+    
+    let fixes = counter_data.sanitize_history();
+    
+    // Serialize and store updated data
+    counter_data.serialize(&mut &mut counter_account.data.borrow_mut()[..])?;
+    
+    msg!("History repaired with {} fixes", fixes);
+    Ok(())
+}
+
+/// Add `member` to the counter's authority set, or remove it
+fn update_authorities(program_id: &Pubkey, accounts: &[AccountInfo], member: Pubkey, add: bool) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    
+    // Get accounts
+    let authority = next_account_info(account_iter)?;
+    let counter_account = next_account_info(account_iter)?;
+    
+    // Deserialize counter account data
+    let mut counter_data = load_counter(program_id, counter_account)?;
+    
+    // Verify authority is signer and in the set
+    verify_authority(counter_data.claimed_authority(authority.key), authority)?;
+    
+    if add {
+        if counter_data.is_authority(&member) {
+            msg!("Error: {} is already an authority", member);
+            return Err(CounterError::DuplicateAuthority.into());
         }
-        CounterInstruction::Increment => {
-            msg!("Instruction: Increment");
-            increment_counter(accounts)
+        if counter_data.authorities.len() >= MAX_AUTHORITIES {
+            msg!("Error: Authority set already holds {} members", MAX_AUTHORITIES + 1);
+            return Err(CounterError::TooManyAuthorities.into());
         }
-        CounterInstruction::Decrement => {
-            msg!("Instruction: Decrement");
-            decrement_counter(accounts)
+        counter_data.authorities.push(member);
+    } else {
+        if !counter_data.is_authority(&member) {
+            msg!("Error: {} is not an authority", member);
+            return Err(CounterError::NotFound.into());
+        }
+        if counter_data.authorities.is_empty() {
+            msg!("Error: Cannot remove the last authority");
+            return Err(CounterError::LastAuthority.into());
+        }
+        if counter_data.authority == member {
+            counter_data.authority = counter_data.authorities.remove(0);
+        } else {
+            counter_data.authorities.retain(|key| *key != member);
         }
     }
+    
+    // Serialize and store updated data
+    counter_data.serialize(&mut &mut counter_account.data.borrow_mut()[..])?;
+    
+    msg!("Authority {} {}", member, if add { "added" } else { "removed" });
+    Ok(())
 }
 
-/// Initialize a new counter account
-fn initialize_counter(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+/// Permanently freeze the counter
+fn freeze_counter(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
     let account_iter = &mut accounts.iter();
     
     // Get accounts
     let authority = next_account_info(account_iter)?;
     let counter_account = next_account_info(account_iter)?;
     
-    // Verify authority is signer
-    if !authority.is_signer {
-        msg!("Error: Authority must be a signer");
+    // Deserialize counter account data
+    let mut counter_data = load_counter(program_id, counter_account)?;
+    
+    // Verify authority is signer and matches
+    verify_authority(&counter_data.authority, authority)?;
+    
+    counter_data.frozen = true;
+    
+    // Serialize and store updated data
+    counter_data.serialize(&mut &mut counter_account.data.borrow_mut()[..])?;
+    
+    msg!("Counter frozen at: {}", counter_data.count);
+    Ok(())
+}
+
+/// Pause or unpause changes to the count
+fn set_paused(program_id: &Pubkey, accounts: &[AccountInfo], paused: bool) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    
+    // Get accounts
+    let authority = next_account_info(account_iter)?;
+    let counter_account = next_account_info(account_iter)?;
+    
+    // Deserialize counter account data
+    let mut counter_data = load_counter(program_id, counter_account)?;
+    
+    // Verify authority is signer and matches
+    verify_authority(&counter_data.authority, authority)?;
+    
+    counter_data.paused = paused;
+    
+    // Serialize and store updated data
+    counter_data.serialize(&mut &mut counter_account.data.borrow_mut()[..])?;
+    
+    msg!("Counter {}", if paused { "paused" } else { "unpaused" });
+    Ok(())
+}
+
+/// Upgrade the counter to the current layout version
+fn migrate_counter(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    
+    // Get accounts
+    let authority = next_account_info(account_iter)?;
+    let counter_account = next_account_info(account_iter)?;
+    
+    // Deserialize counter account data in whatever version it was stored
+    verify_counter_account(program_id, counter_account)?;
+    let counter_data = CounterAccount::upgrade(&counter_account.data.borrow())?;
+    counter_data.verify_address(program_id, counter_account.key)?;
+    
+    // Verify authority is signer and matches
+    verify_authority(&counter_data.authority, authority)?;
+    
+    let stored_version = CounterAccount::stored_version(&counter_account.data.borrow())?;
+    if stored_version == CounterAccount::VERSION {
+        msg!("Counter already at version {}", stored_version);
+        return Ok(());
+    }
+    
+    // Older layouts were allocated smaller; grow to fit the largest current state
+    if counter_account.data_len() < CounterAccount::LEN {
+        grow_account(counter_account, CounterAccount::LEN, account_iter.as_slice())?;
+    }
+    
+    // Serialize and store updated data
+    counter_data.serialize(&mut &mut counter_account.data.borrow_mut()[..])?;
+    
+    msg!("Counter migrated from version {} to {}", stored_version, CounterAccount::VERSION);
+    Ok(())
+}
+
+/// Reallocate `account` to `new_len` bytes, topping it up to stay rent exempt
+/// Takes a payer and the system program from `funding_accounts`.
+fn grow_account<'a>(
+    account: &AccountInfo<'a>,
+    new_len: usize,
+    funding_accounts: &[AccountInfo<'a>],
+) -> ProgramResult {
+    let funding_iter = &mut funding_accounts.iter();
+    let payer = next_account_info(funding_iter)?;
+    let system_program = next_account_info(funding_iter)?;
+    
+    // Verify payer is signer
+    if !payer.is_signer {
+        msg!("Error: Payer must be a signer");
         return Err(ProgramError::MissingRequiredSignature);
     }
     
-    // Verify counter account is owned by our program
-    if counter_account.owner != program_id {
-        msg!("Error: Counter account not owned by program");
-        return Err(ProgramError::IncorrectProgramId);
+    msg!("Growing account from {} to {} bytes", account.data_len(), new_len);
+    account.realloc(new_len, false)?;
+    
+    // Top up rent for the extra space
+    ensure_rent_exempt(account, payer, system_program)
+}
+
+/// Transfer whatever `account` lacks to be rent exempt at its current size from `payer`
+/// Does nothing for an account already holding enough lamports.
+fn ensure_rent_exempt<'a>(
+    account: &AccountInfo<'a>,
+    payer: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+) -> ProgramResult {
+    let required = Rent::get()?.minimum_balance(account.data_len());
+    let shortfall = required.saturating_sub(account.lamports());
+    if shortfall > 0 {
+        msg!("Topping up {} lamports for rent", shortfall);
+        invoke(
+            &system_instruction::transfer(payer.key, account.key, shortfall),
+            &[payer.clone(), account.clone(), system_program.clone()],
+        )?;
     }
+    Ok(())
+}
+
+/// Serialize `state` into `account`, reallocating it first when the state no longer fits
+/// Growing takes a payer and the system program from `funding_accounts`, which
+/// tops the account up to stay rent exempt at its new size.
+fn store_with_realloc<'a, T: BorshSerialize>(
+    state: &T,
+    account: &AccountInfo<'a>,
+    funding_accounts: &[AccountInfo<'a>],
+) -> ProgramResult {
+    let data = state.try_to_vec()?;
     
-    // Check if account is already initialized
-    if !counter_account.data_is_empty() {
-        msg!("Error: Counter account already initialized");
-        return Err(ProgramError::AccountAlreadyInitialized);
+    if data.len() > account.data_len() {
+        grow_account(account, data.len(), funding_accounts)?;
     }
     
-    // Verify account has enough space
-    let account_len = std::mem::size_of::<CounterAccount>();
-    if counter_account.data_len() < account_len {
-        msg!("Error: Counter account too small");
-        return Err(ProgramError::AccountDataTooSmall);
+    account.data.borrow_mut()[..data.len()].copy_from_slice(&data);
+    Ok(())
+}
+
+/// Decode the program config account, verifying it is the config PDA
+fn load_config(program_id: &Pubkey, config_account: &AccountInfo) -> Result<ConfigAccount, ProgramError> {
+    let (expected_address, _) = ConfigAccount::address(program_id);
+    if expected_address != *config_account.key {
+        msg!("Error: Config account does not match derived address {}", expected_address);
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if config_account.owner != program_id {
+        msg!("Error: Config account not initialized");
+        return Err(CounterError::NotInitialized.into());
     }
+    ConfigAccount::try_from_slice(&config_account.data.borrow()).map_err(|_| ProgramError::InvalidAccountData)
+}
+
+/// Create the program config account at its PDA
+fn init_config(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    default_max_value: u64,
+    default_cooldown_secs: i64,
+) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
     
-    // Verify account is rent exempt
-    let rent = Rent::get()?;
-    if !rent.is_exempt(counter_account.lamports(), account_len) {
-        msg!("Error: Counter account not rent exempt");
-        return Err(ProgramError::AccountNotRentExempt);
+    // Get accounts
+    let admin = next_account_info(account_iter)?;
+    let config_account = next_account_info(account_iter)?;
+    let system_program = next_account_info(account_iter)?;
+    
+    // Verify admin is signer
+    if !admin.is_signer {
+        msg!("Error: Admin must be a signer");
+        return Err(ProgramError::MissingRequiredSignature);
     }
     
-    // Initialize the counter account
-    let counter_data = CounterAccount {
-        count: 0,
-        authority: *authority.key,
-    };
+    // Verify the cooldown is never negative
+    if default_cooldown_secs < 0 {
+        msg!("Error: Cooldown must not be negative");
+        return Err(CounterError::InvalidCooldown.into());
+    }
     
-    // Serialize and store data
-    counter_data.serialize(&mut *counter_account.data.borrow_mut())?;
+    // Verify the passed account is the derived address
+    let (expected_address, bump) = ConfigAccount::address(program_id);
+    if expected_address != *config_account.key {
+        msg!("Error: Config account does not match derived address {}", expected_address);
+        return Err(ProgramError::InvalidSeeds);
+    }
     
-    msg!("Counter initialized successfully with count: {}", counter_data.count);
+    // Create the account, signing for the PDA; fails if it already exists
+    let lamports = Rent::get()?.minimum_balance(ConfigAccount::LEN);
+    invoke_signed(
+        &system_instruction::create_account(
+            admin.key,
+            config_account.key,
+            lamports,
+            ConfigAccount::LEN as u64,
+            program_id,
+        ),
+        &[admin.clone(), config_account.clone(), system_program.clone()],
+        &[&[CONFIG_SEED, &[bump]]],
+    )?;
+    
+    let config = ConfigAccount {
+        admin: *admin.key,
+        default_max_value,
+        default_cooldown_secs,
+    };
+    config.serialize(&mut &mut config_account.data.borrow_mut()[..])?;
+    
+    msg!("Program config created with admin: {}", admin.key);
     Ok(())
 }
 
-/// Increment the counter
-fn increment_counter(accounts: &[AccountInfo]) -> ProgramResult {
+/// Change the program defaults
+fn update_config(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    default_max_value: u64,
+    default_cooldown_secs: i64,
+) -> ProgramResult {
     let account_iter = &mut accounts.iter();
     
     // Get accounts
-    let authority = next_account_info(account_iter)?;
-    let counter_account = next_account_info(account_iter)?;
+    let admin = next_account_info(account_iter)?;
+    let config_account = next_account_info(account_iter)?;
     
-    // Verify authority is signer
-    if !authority.is_signer {
-        msg!("Error: Authority must be a signer");
-        return Err(ProgramError::MissingRequiredSignature);
-    }
+    // Deserialize config account data
+    let mut config = load_config(program_id, config_account)?;
     
-    // Deserialize counter account data
-    let mut counter_data = CounterAccount::try_from_slice(&counter_account.data.borrow())?;
+    // Verify admin is signer and matches
+    verify_authority(&config.admin, admin)?;
     
-    // Verify authority matches
-    if counter_data.authority != *authority.key {
-        msg!("Error: Authority mismatch");
-        return Err(ProgramError::InvalidAccountData);
+    // Verify the cooldown is never negative
+    if default_cooldown_secs < 0 {
+        msg!("Error: Cooldown must not be negative");
+        return Err(CounterError::InvalidCooldown.into());
     }
     
-    // Increment counter (with overflow protection)
-    counter_data.count = counter_data.count
-        .checked_add(1)
-        .ok_or(ProgramError::ArithmeticOverflow)?;
+    config.default_max_value = default_max_value;
+    config.default_cooldown_secs = default_cooldown_secs;
     
     // Serialize and store updated data
-    counter_data.serialize(&mut *counter_account.data.borrow_mut())?;
+    config.serialize(&mut &mut config_account.data.borrow_mut()[..])?;
     
-    msg!("Counter incremented to: {}", counter_data.count);
+    msg!("Program config updated");
     Ok(())
 }
 
-/// Decrement the counter
-fn decrement_counter(accounts: &[AccountInfo]) -> ProgramResult {
+/// Decode a multi-counter account owned by this program
+fn load_multi_counter(program_id: &Pubkey, multi_account: &AccountInfo) -> Result<MultiCounterAccount, ProgramError> {
+    if multi_account.owner != program_id {
+        msg!("Error: Multi-counter account not owned by program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    MultiCounterAccount::unpack(&multi_account.data.borrow())
+}
+
+/// Initialize an empty multi-counter account
+fn initialize_multi_counter(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
     let account_iter = &mut accounts.iter();
     
     // Get accounts
     let authority = next_account_info(account_iter)?;
-    let counter_account = next_account_info(account_iter)?;
+    let multi_account = next_account_info(account_iter)?;
     
     // Verify authority is signer
     if !authority.is_signer {
@@ -177,30 +3292,96 @@ fn decrement_counter(accounts: &[AccountInfo]) -> ProgramResult {
         return Err(ProgramError::MissingRequiredSignature);
     }
     
-    // Deserialize counter account data
-    let mut counter_data = CounterAccount::try_from_slice(&counter_account.data.borrow())?;
+    // Verify multi-counter account is owned by our program
+    if multi_account.owner != program_id {
+        msg!("Error: Multi-counter account not owned by program");
+        return Err(ProgramError::IncorrectProgramId);
+    }
     
-    // Verify authority matches
-    if counter_data.authority != *authority.key {
-        msg!("Error: Authority mismatch");
-        return Err(ProgramError::InvalidAccountData);
+    // Check if account is already initialized (freshly allocated accounts are zero-filled)
+    if multi_account.data.borrow().iter().any(|byte| *byte != 0) {
+        msg!("Error: Multi-counter account already initialized");
+        return Err(CounterError::AlreadyInitialized.into());
     }
     
-    // Decrement counter (with underflow protection)
-    counter_data.count = counter_data.count
-        .checked_sub(1)
-        .ok_or(ProgramError::ArithmeticOverflow)?;
+    // Verify account is rent exempt
+    let rent = Rent::get()?;
+    if !rent.is_exempt(multi_account.lamports(), multi_account.data_len()) {
+        msg!("Error: Multi-counter account not rent exempt");
+        return Err(ProgramError::AccountNotRentExempt);
+    }
     
-    // Serialize and store updated data
-    counter_data.serialize(&mut *counter_account.data.borrow_mut())?;
+    let multi_data = MultiCounterAccount {
+        authority: *authority.key,
+        counters: Vec::new(),
+    };
+    multi_data.serialize(&mut &mut multi_account.data.borrow_mut()[..])?;
     
-    msg!("Counter decremented to: {}", counter_data.count);
+    msg!("Multi-counter initialized");
     Ok(())
 }
 
-// Error handling utilities
-impl From<std::io::Error> for ProgramError {
-    fn from(_: std::io::Error) -> Self {
-        ProgramError::InvalidAccountData
+/// Add a new counter called `name` to a multi-counter account
+fn add_named_counter(program_id: &Pubkey, accounts: &[AccountInfo], name: String) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    
+    // Get accounts
+    let authority = next_account_info(account_iter)?;
+    let multi_account = next_account_info(account_iter)?;
+    
+    // Deserialize multi-counter account data
+    let mut multi_data = load_multi_counter(program_id, multi_account)?;
+    
+    // Verify authority is signer and matches
+    verify_authority(&multi_data.authority, authority)?;
+    
+    // Verify the name fits and is not already used
+    if name.len() > MultiCounterAccount::MAX_NAME_LEN {
+        msg!("Error: Counter name longer than {} bytes", MultiCounterAccount::MAX_NAME_LEN);
+        return Err(ProgramError::InvalidArgument);
+    }
+    if multi_data.get(&name).is_some() {
+        msg!("Error: A counter named {:?} already exists", name);
+        return Err(CounterError::DuplicateName.into());
+    }
+    if multi_data.counters.len() >= MAX_NAMED_COUNTERS {
+        msg!("Error: Multi-counter already holds {} counters", MAX_NAMED_COUNTERS);
+        return Err(CounterError::TooLarge.into());
     }
+    
+    msg!("Added counter {:?}", name);
+    multi_data.counters.push((name, 0));
+    
+    // Serialize and store updated data, growing the account if needed
+    store_with_realloc(&multi_data, multi_account, account_iter.as_slice())
+}
+
+/// Increment (or decrement) the counter called `name` by one
+fn step_named_counter(program_id: &Pubkey, accounts: &[AccountInfo], name: &str, increment: bool) -> ProgramResult {
+    let account_iter = &mut accounts.iter();
+    
+    // Get accounts
+    let authority = next_account_info(account_iter)?;
+    let multi_account = next_account_info(account_iter)?;
+    
+    // Deserialize multi-counter account data
+    let mut multi_data = load_multi_counter(program_id, multi_account)?;
+    
+    // Verify authority is signer and matches
+    verify_authority(&multi_data.authority, authority)?;
+    
+    // Update the named counter (with overflow and underflow protection)
+    let value = multi_data.value_mut(name)?;
+    *value = if increment {
+        value.checked_add(1).ok_or(CounterError::Overflow)?
+    } else {
+        value.checked_sub(1).ok_or(CounterError::Underflow)?
+    };
+    let new_value = *value;
+    
+    // Serialize and store updated data
+    multi_data.serialize(&mut &mut multi_account.data.borrow_mut()[..])?;
+    
+    msg!("Counter {:?} set to: {}", name, new_value);
+    Ok(())
 }
@@ -0,0 +1,22 @@
+//! Borsh schemas describing the program's on-chain types
+//! Clients in other languages can generate matching (de)serializers from these
+//! instead of hand-writing the layouts
+
+use std::{fs, io, path::Path};
+
+use borsh::{schema::BorshSchemaContainer, BorshSchema, BorshSerialize};
+
+use crate::{CounterAccount, CounterInstruction};
+
+/// Schemas for `CounterAccount` followed by `CounterInstruction`
+pub fn schemas() -> Vec<BorshSchemaContainer> {
+    vec![
+        CounterAccount::schema_container(),
+        CounterInstruction::schema_container(),
+    ]
+}
+
+/// Write the Borsh encoded `schemas()` to `path`
+pub fn write_schema(path: &Path) -> io::Result<()> {
+    fs::write(path, schemas().try_to_vec()?)
+}
@@ -0,0 +1,102 @@
+//! Base58 encoding of public keys for the `serde` derives
+//! `Pubkey` itself serializes as 32 raw bytes; fields marked
+//! `#[serde(with = "crate::serde_base58")]` use the base58 strings explorers and
+//! wallets show instead.
+//!
+//! `CounterInstruction` variants holding a single key can't mark the field: the
+//! `schema` feature's `BorshSchema` derive copies field attributes into structs
+//! serde never sees. They mark the variant with the adapter named after the
+//! field instead, e.g. `crate::serde_base58::member`, which writes the variant as
+//! `{"member": "<base58>"}` -- the same shape as every other struct variant.
+
+use std::str::FromStr;
+
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+use solana_program::pubkey::Pubkey;
+
+/// Field types holding public keys, converted to and from base58 strings
+pub trait Base58: Sized {
+    /// The field with every key replaced by its base58 string
+    type Encoded: Serialize + for<'de> Deserialize<'de>;
+
+    /// Replace every key with its base58 string
+    fn encode(&self) -> Self::Encoded;
+
+    /// Parse every base58 string back into a key
+    fn decode(encoded: Self::Encoded) -> Result<Self, String>;
+}
+
+impl Base58 for Pubkey {
+    type Encoded = String;
+
+    fn encode(&self) -> String {
+        self.to_string()
+    }
+
+    fn decode(encoded: String) -> Result<Self, String> {
+        Pubkey::from_str(&encoded).map_err(|err| format!("invalid public key {:?}: {}", encoded, err))
+    }
+}
+
+impl Base58 for Option<Pubkey> {
+    type Encoded = Option<String>;
+
+    fn encode(&self) -> Option<String> {
+        self.as_ref().map(Pubkey::encode)
+    }
+
+    fn decode(encoded: Option<String>) -> Result<Self, String> {
+        encoded.map(Pubkey::decode).transpose()
+    }
+}
+
+impl Base58 for Vec<Pubkey> {
+    type Encoded = Vec<String>;
+
+    fn encode(&self) -> Vec<String> {
+        self.iter().map(Pubkey::encode).collect()
+    }
+
+    fn decode(encoded: Vec<String>) -> Result<Self, String> {
+        encoded.into_iter().map(Pubkey::decode).collect()
+    }
+}
+
+/// Serialize the keys in `value` as base58 strings
+pub fn serialize<T: Base58, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    value.encode().serialize(serializer)
+}
+
+/// Deserialize keys written as base58 strings
+pub fn deserialize<'de, T: Base58, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+    T::decode(T::Encoded::deserialize(deserializer)?).map_err(D::Error::custom)
+}
+
+/// Generate a variant adapter per field name, wrapping the keys in a struct
+/// holding that one field
+macro_rules! variant_field {
+    ($($field:ident),*) => {$(
+        #[doc = concat!("Adapter for variants whose only field is `", stringify!($field), "`")]
+        pub mod $field {
+            use super::*;
+
+            #[derive(Serialize, Deserialize)]
+            struct Fields<T> {
+                $field: T,
+            }
+
+            /// Serialize the variant's field with its keys as base58 strings
+            pub fn serialize<T: Base58, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+                Fields { $field: value.encode() }.serialize(serializer)
+            }
+
+            /// Deserialize the variant's field from base58 strings
+            pub fn deserialize<'de, T: Base58, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+                let fields = Fields::<T::Encoded>::deserialize(deserializer)?;
+                T::decode(fields.$field).map_err(D::Error::custom)
+            }
+        }
+    )*};
+}
+
+variant_field!(authorities, authority, delegate, member, new_authority);
@@ -1,17 +1,504 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use borsh::{BorshDeserialize, BorshSerialize};
-use simple_solana_program::{CounterAccount, CounterInstruction};
+use simple_solana_program::{
+    client::{ClientError, CounterClient},
+    count_of, counter_address, counter_address_for, counter_generation_address, decode_counter, extract_events,
+    get_count_checked, instruction_builders, read_counter, to_decimal, try_read_count, verify_authority,
+    ArithmeticMode, CounterAccount, CounterConfig,
+    CounterError, CounterEvent, CounterInstruction, ConfigAccount, CounterData, CounterKind, CounterValue,
+    MilestoneReached, MultiCounterAccount, MultisigConfig, PdaSeeds, SimulatedInstruction, SimulationOutcome, COUNTER_DISCRIMINATOR,
+    COUNTER_SEED_PREFIX, HISTORY_LEN, MAX_AUTHORITIES, MAX_BASIS_POINTS, MAX_BATCH_OPS, MAX_LABEL_LEN,
+    MAX_SCALE,
+};
 use solana_program::{
-    instruction::{AccountMeta, Instruction},
+    account_info::AccountInfo,
+    clock::Clock,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    msg,
+    program_error::ProgramError,
+    program_stubs::{self, SyscallStubs},
     pubkey::Pubkey,
     rent::Rent,
-    system_instruction,
+    system_instruction, system_program,
 };
+use serde_json::json;
+use solana_client::{rpc_client::RpcClient, rpc_request::RpcRequest};
 use solana_program_test::*;
 use solana_sdk::{
-    account::Account,
+    account::AccountSharedData,
     signature::{Keypair, Signer},
-    transaction::Transaction,
+    transaction::{Transaction, TransactionError},
 };
+use std::{collections::HashMap, sync::Once};
+
+/// Start a local runtime with the counter program loaded
+async fn start_program() -> (Pubkey, ProgramTestContext) {
+    start_program_with(ProgramTest::default()).await
+}
+
+/// Start a local runtime with the counter program added to `program_test`
+async fn start_program_with(mut program_test: ProgramTest) -> (Pubkey, ProgramTestContext) {
+    let program_id = Pubkey::new_unique();
+    program_test.add_program(
+        "simple_solana_program",
+        program_id,
+        processor!(simple_solana_program::process_instruction),
+    );
+    let context = program_test.start_with_context().await;
+    LOG_DATA_STUBS.call_once(|| {
+        let inner = program_stubs::set_syscall_stubs(Box::new(NoopStubs));
+        program_stubs::set_syscall_stubs(Box::new(LogDataStubs(inner)));
+    });
+    (program_id, context)
+}
+
+static LOG_DATA_STUBS: Once = Once::new();
+
+/// Placeholder held only while swapping in `LogDataStubs`
+struct NoopStubs;
+impl SyscallStubs for NoopStubs {}
+
+/// Native builds of program-test print `sol_log_data` to stdout instead of
+/// the transaction log, so route it through `sol_log` where tests can see it.
+/// Every other syscall is forwarded to the program-test stubs.
+struct LogDataStubs(Box<dyn SyscallStubs>);
+
+impl SyscallStubs for LogDataStubs {
+    fn sol_log(&self, message: &str) {
+        self.0.sol_log(message)
+    }
+    fn sol_log_compute_units(&self) {
+        self.0.sol_log_compute_units()
+    }
+    fn sol_remaining_compute_units(&self) -> u64 {
+        self.0.sol_remaining_compute_units()
+    }
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        self.0.sol_invoke_signed(instruction, account_infos, signers_seeds)
+    }
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_clock_sysvar(var_addr)
+    }
+    fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_epoch_schedule_sysvar(var_addr)
+    }
+    fn sol_get_fees_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_fees_sysvar(var_addr)
+    }
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_rent_sysvar(var_addr)
+    }
+    fn sol_get_epoch_rewards_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_epoch_rewards_sysvar(var_addr)
+    }
+    fn sol_get_last_restart_slot(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_last_restart_slot(var_addr)
+    }
+    unsafe fn sol_memcpy(&self, dst: *mut u8, src: *const u8, n: usize) {
+        self.0.sol_memcpy(dst, src, n)
+    }
+    unsafe fn sol_memmove(&self, dst: *mut u8, src: *const u8, n: usize) {
+        self.0.sol_memmove(dst, src, n)
+    }
+    unsafe fn sol_memcmp(&self, s1: *const u8, s2: *const u8, n: usize, result: *mut i32) {
+        self.0.sol_memcmp(s1, s2, n, result)
+    }
+    unsafe fn sol_memset(&self, s: *mut u8, c: u8, n: usize) {
+        self.0.sol_memset(s, c, n)
+    }
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        self.0.sol_get_return_data()
+    }
+    fn sol_set_return_data(&self, data: &[u8]) {
+        self.0.sol_set_return_data(data)
+    }
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        let encoded: Vec<String> = fields.iter().map(|field| BASE64.encode(field)).collect();
+        self.0.sol_log(&format!("Program data: {}", encoded.join(" ")))
+    }
+    fn sol_get_processed_sibling_instruction(&self, index: usize) -> Option<Instruction> {
+        self.0.sol_get_processed_sibling_instruction(index)
+    }
+    fn sol_get_stack_height(&self) -> u64 {
+        self.0.sol_get_stack_height()
+    }
+}
+
+/// Sign a transaction paid for by the context payer.
+/// A fresh blockhash is fetched each time so repeated identical
+/// instructions are not rejected as duplicates.
+async fn sign(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    extra_signers: &[&Keypair],
+) -> Transaction {
+    let recent_blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut signers = vec![&context.payer];
+    signers.extend_from_slice(extra_signers);
+    Transaction::new_signed_with_payer(
+        instructions,
+        Some(&context.payer.pubkey()),
+        &signers,
+        recent_blockhash,
+    )
+}
+
+/// Sign and send a transaction paid for by the context payer
+async fn send(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    extra_signers: &[&Keypair],
+) -> Result<(), TransactionError> {
+    let transaction = sign(context, instructions, extra_signers).await;
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|err| err.unwrap())
+}
+
+/// Send a transaction that must succeed and return its program logs
+async fn send_with_logs(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    extra_signers: &[&Keypair],
+) -> Vec<String> {
+    let transaction = sign(context, instructions, extra_signers).await;
+    let outcome = context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    outcome.result.unwrap();
+    outcome.metadata.unwrap().log_messages
+}
+
+/// Start a local runtime running the program's BPF build instead of the native processor,
+/// so compute units are metered as they would be on chain
+#[cfg(feature = "test-bpf")]
+async fn start_bpf_program() -> (Pubkey, ProgramTestContext) {
+    let mut program_test = ProgramTest::default();
+    program_test.prefer_bpf(true);
+    start_program_with(program_test).await
+}
+
+/// Send a transaction that must succeed and return the compute units it consumed
+#[cfg(feature = "test-bpf")]
+async fn compute_units(context: &mut ProgramTestContext, instructions: &[Instruction]) -> u64 {
+    let transaction = sign(context, instructions, &[]).await;
+    let outcome = context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    outcome.result.unwrap();
+    outcome.metadata.unwrap().compute_units_consumed
+}
+
+/// Send a transaction that must succeed and return the data its last instruction returned
+async fn return_data(context: &mut ProgramTestContext, instructions: &[Instruction]) -> Vec<u8> {
+    let transaction = sign(context, instructions, &[]).await;
+    let outcome = context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    outcome.result.unwrap();
+    outcome.metadata.unwrap().return_data.map_or_else(Vec::new, |return_data| return_data.data)
+}
+
+/// Build an instruction taking the standard `[authority, counter]` accounts
+fn counter_ix(
+    program_id: Pubkey,
+    instruction: CounterInstruction,
+    authority: &Pubkey,
+    counter: &Pubkey,
+) -> Instruction {
+    Instruction::new_with_bytes(
+        program_id,
+        &instruction.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*counter, false),
+        ],
+    )
+}
+
+/// Create and initialize a counter whose authority is the context payer
+async fn create_counter(context: &mut ProgramTestContext, program_id: Pubkey) -> Pubkey {
+    create_counter_with(context, program_id, CounterInstruction::Initialize).await
+}
+
+/// Create a counter and initialize it with the given initialize instruction
+async fn create_counter_with(
+    context: &mut ProgramTestContext,
+    program_id: Pubkey,
+    initialize: CounterInstruction,
+) -> Pubkey {
+    try_create_counter_with(context, program_id, initialize)
+        .await
+        .unwrap()
+}
+
+/// Allocate a counter account and run `initialize` against it in one transaction.
+/// The initialize instruction is the second instruction of the transaction.
+async fn try_create_counter_with(
+    context: &mut ProgramTestContext,
+    program_id: Pubkey,
+    initialize: CounterInstruction,
+) -> Result<Pubkey, TransactionError> {
+    let counter_keypair = Keypair::new();
+    let counter_pubkey = counter_keypair.pubkey();
+
+    let account_space = CounterAccount::LEN;
+    let rent_exemption = Rent::default().minimum_balance(account_space);
+    let payer = context.payer.pubkey();
+
+    let create_account_ix = system_instruction::create_account(
+        &payer,
+        &counter_pubkey,
+        rent_exemption,
+        account_space as u64,
+        &program_id,
+    );
+    let initialize_ix = counter_ix(program_id, initialize, &payer, &counter_pubkey);
+
+    send(context, &[create_account_ix, initialize_ix], &[&counter_keypair]).await?;
+    Ok(counter_pubkey)
+}
+
+/// The transaction error produced when the first instruction fails with `error`
+fn counter_error(error: CounterError) -> TransactionError {
+    counter_error_at(0, error)
+}
+
+/// The transaction error produced when instruction `index` fails with `error`
+fn counter_error_at(index: u8, error: CounterError) -> TransactionError {
+    TransactionError::InstructionError(index, InstructionError::Custom(error as u32))
+}
+
+/// Fetch and decode a counter account
+async fn get_counter(context: &mut ProgramTestContext, counter: Pubkey) -> CounterAccount {
+    let account = context.banks_client.get_account(counter).await.unwrap().unwrap();
+    CounterAccount::unpack(&account.data).unwrap()
+}
+
+/// Build an `InitializePda` instruction for the PDA derived from `authority` and `seed`
+fn initialize_pda_ix(program_id: Pubkey, authority: &Pubkey, seed: &str) -> (Instruction, Pubkey, u8) {
+    let (counter, bump) = Pubkey::find_program_address(
+        &[COUNTER_SEED_PREFIX, authority.as_ref(), seed.as_bytes()],
+        &program_id,
+    );
+    let instruction = Instruction::new_with_bytes(
+        program_id,
+        &CounterInstruction::InitializePda {
+            seed: seed.to_string(),
+        }
+        .try_to_vec()
+        .unwrap(),
+        vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(counter, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    (instruction, counter, bump)
+}
+
+/// Build an `InitConfig` instruction creating the config PDA with `admin` as admin
+fn init_config_ix(
+    program_id: Pubkey,
+    admin: &Pubkey,
+    default_max_value: u64,
+    default_cooldown_secs: i64,
+) -> Instruction {
+    let (config, _) = ConfigAccount::address(&program_id);
+    Instruction::new_with_bytes(
+        program_id,
+        &CounterInstruction::InitConfig {
+            default_max_value,
+            default_cooldown_secs,
+        }
+        .try_to_vec()
+        .unwrap(),
+        vec![
+            AccountMeta::new(*admin, true),
+            AccountMeta::new(config, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// Require a 2-of-3 multisig on a fresh counter, returning the counter and members
+async fn create_multisig_counter(
+    context: &mut ProgramTestContext,
+    program_id: Pubkey,
+) -> (Pubkey, [Keypair; 3]) {
+    let counter = create_counter(context, program_id).await;
+    let authority = context.payer.pubkey();
+    let members = [Keypair::new(), Keypair::new(), Keypair::new()];
+
+    let set_multisig_ix = counter_ix(
+        program_id,
+        CounterInstruction::SetMultisig {
+            multisig: Some(MultisigConfig {
+                signers: members.iter().map(|member| member.pubkey()).collect(),
+                threshold: 2,
+            }),
+        },
+        &authority,
+        &counter,
+    );
+    send(context, &[set_multisig_ix], &[]).await.unwrap();
+    (counter, members)
+}
+
+/// Create and initialize a multi-counter account with room for `capacity` names
+async fn create_multi_counter(
+    context: &mut ProgramTestContext,
+    program_id: Pubkey,
+    capacity: usize,
+) -> Pubkey {
+    let multi_keypair = Keypair::new();
+    let multi_pubkey = multi_keypair.pubkey();
+
+    let account_space = MultiCounterAccount::space(capacity);
+    let rent_exemption = Rent::default().minimum_balance(account_space);
+    let payer = context.payer.pubkey();
+
+    let create_account_ix = system_instruction::create_account(
+        &payer,
+        &multi_pubkey,
+        rent_exemption,
+        account_space as u64,
+        &program_id,
+    );
+    let initialize_ix = counter_ix(program_id, CounterInstruction::InitializeMulti, &payer, &multi_pubkey);
+
+    send(context, &[create_account_ix, initialize_ix], &[&multi_keypair])
+        .await
+        .unwrap();
+    multi_pubkey
+}
+
+/// Fetch and decode a multi-counter account
+async fn get_multi_counter(context: &mut ProgramTestContext, multi: Pubkey) -> MultiCounterAccount {
+    let account = context.banks_client.get_account(multi).await.unwrap().unwrap();
+    MultiCounterAccount::unpack(&account.data).unwrap()
+}
+
+/// A current-version counter at 0 with default settings, as `Initialize` would write it
+fn sample_counter(authority: Pubkey) -> CounterAccount {
+    CounterAccount {
+        discriminator: COUNTER_DISCRIMINATOR,
+        version: CounterAccount::VERSION,
+        count: CounterValue(0),
+        authority,
+        last_updated: 1_700_000_000,
+        update_count: 0,
+        min_value: CounterValue(0),
+        max_value: CounterValue(u64::MAX),
+        step: 1,
+        mode: ArithmeticMode::Checked,
+        kind: CounterKind::Unsigned,
+        bump: 0,
+        milestone_fired: false,
+        rate_limited: false,
+        last_slot: 0,
+        cooldown_secs: 0,
+        history: [CounterValue(0); HISTORY_LEN],
+        history_pos: 0,
+        last_delta: 0,
+        frozen: false,
+        paused: false,
+        processing: false,
+        flags: 0,
+        pending_authority: None,
+        pda_seeds: None,
+        multisig: None,
+        delegate: None,
+        milestone: None,
+        milestone_target: None,
+        previous_count: None,
+        initial_count: CounterValue(0),
+        auto_reset: false,
+        authorities: Vec::new(),
+        label: String::new(),
+        nonce: 0,
+        epoch_window: false,
+        last_epoch: 0,
+        index: 0,
+        decay_per_sec: 0,
+        strict_signers: false,
+        scale: 0,
+        expires_at: 0,
+    }
+}
+
+/// Build a `TransferUnits` instruction moving `amount` from `source` to `destination`
+fn transfer_units_ix(
+    program_id: Pubkey,
+    authority: &Pubkey,
+    source: &Pubkey,
+    destination: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    let instruction = CounterInstruction::TransferUnits { amount };
+    let mut instruction = counter_ix(program_id, instruction, authority, source);
+    instruction.accounts.push(AccountMeta::new(*destination, false));
+    instruction
+}
+
+/// Store raw counter data in a new rent-exempt account owned by the program
+fn store_counter_data(context: &mut ProgramTestContext, program_id: Pubkey, data: Vec<u8>) -> Pubkey {
+    let counter = Pubkey::new_unique();
+    let mut account = AccountSharedData::new(
+        Rent::default().minimum_balance(data.len()),
+        data.len(),
+        &program_id,
+    );
+    account.set_data_from_slice(&data);
+    context.set_account(&counter, &account);
+    counter
+}
+
+/// Move the bank's clock `seconds` into the future
+async fn advance_clock(context: &mut ProgramTestContext, seconds: i64) {
+    let mut clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    clock.unix_timestamp += seconds;
+    context.set_sysvar(&clock);
+}
+
+/// Mock milestone target that logs each `MilestoneReached` it receives
+fn record_milestone(_program_id: &Pubkey, _accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let reached = MilestoneReached::try_from_slice(data)?;
+    msg!("Milestone callback: {} reached {}", reached.counter, reached.count);
+    Ok(())
+}
+
+/// Mock program running `verify_authority` against the key in its instruction data
+fn check_authority(_program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let stored = Pubkey::try_from_slice(data)?;
+    verify_authority(&stored, &accounts[0])
+}
+
+/// Mock program reading a counter owned by the program in its instruction data,
+/// failing unless the count equals the expected count that follows it
+fn check_count(_program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let (counter_program, expected) = <(Pubkey, u64)>::try_from_slice(data)?;
+    let count = get_count_checked(&accounts[0], &counter_program)?;
+    msg!("Read count: {}", count);
+    if count != expected {
+        return Err(ProgramError::InvalidArgument);
+    }
+    Ok(())
+}
 
 /**
  * Integration tests for the Simple Counter Program
@@ -36,7 +523,7 @@ async fn test_initialize_counter() {
 
     // Calculate rent exemption
     let rent = Rent::default();
-    let account_space = std::mem::size_of::<CounterAccount>();
+    let account_space = CounterAccount::LEN;
     let rent_exemption = rent.minimum_balance(account_space);
 
     // Create account instruction
@@ -70,7 +557,7 @@ async fn test_initialize_counter() {
 
     // Verify counter was initialized
     let counter_account = banks_client.get_account(counter_pubkey).await.unwrap().unwrap();
-    let counter_data = CounterAccount::try_from_slice(&counter_account.data).unwrap();
+    let counter_data = CounterAccount::unpack(&counter_account.data).unwrap();
     
     assert_eq!(counter_data.count, 0);
     assert_eq!(counter_data.authority, payer.pubkey());
@@ -93,7 +580,7 @@ async fn test_increment_counter() {
 
     // Initialize counter (setup)
     let rent = Rent::default();
-    let account_space = std::mem::size_of::<CounterAccount>();
+    let account_space = CounterAccount::LEN;
     let rent_exemption = rent.minimum_balance(account_space);
 
     let create_account_ix = system_instruction::create_account(
@@ -104,15 +591,7 @@ async fn test_increment_counter() {
         &program_id,
     );
 
-    let initialize_data = CounterInstruction::Initialize.try_to_vec().unwrap();
-    let initialize_ix = Instruction::new_with_bytes(
-        program_id,
-        &initialize_data,
-        vec![
-            AccountMeta::new(payer.pubkey(), true),
-            AccountMeta::new(counter_pubkey, false),
-        ],
-    );
+    let initialize_ix = instruction_builders::initialize(&program_id, &payer.pubkey(), &counter_pubkey);
 
     let mut setup_transaction = Transaction::new_with_payer(
         &[create_account_ix, initialize_ix],
@@ -122,15 +601,7 @@ async fn test_increment_counter() {
     banks_client.process_transaction(setup_transaction).await.unwrap();
 
     // Now test increment
-    let increment_data = CounterInstruction::Increment.try_to_vec().unwrap();
-    let increment_ix = Instruction::new_with_bytes(
-        program_id,
-        &increment_data,
-        vec![
-            AccountMeta::new(payer.pubkey(), true),
-            AccountMeta::new(counter_pubkey, true),
-        ],
-    );
+    let increment_ix = instruction_builders::increment(&program_id, &payer.pubkey(), &counter_pubkey);
 
     let mut increment_transaction = Transaction::new_with_payer(
         &[increment_ix],
@@ -142,7 +613,7 @@ async fn test_increment_counter() {
 
     // Verify counter incremented
     let counter_account = banks_client.get_account(counter_pubkey).await.unwrap().unwrap();
-    let counter_data = CounterAccount::try_from_slice(&counter_account.data).unwrap();
+    let counter_data = CounterAccount::unpack(&counter_account.data).unwrap();
     
     assert_eq!(counter_data.count, 1);
 }
@@ -164,7 +635,7 @@ async fn test_decrement_counter() {
 
     // Initialize and increment to have count = 1
     let rent = Rent::default();
-    let account_space = std::mem::size_of::<CounterAccount>();
+    let account_space = CounterAccount::LEN;
     let rent_exemption = rent.minimum_balance(account_space);
 
     let create_account_ix = system_instruction::create_account(
@@ -191,7 +662,7 @@ async fn test_decrement_counter() {
         &increment_data,
         vec![
             AccountMeta::new(payer.pubkey(), true),
-            AccountMeta::new(counter_pubkey, true),
+            AccountMeta::new(counter_pubkey, false),
         ],
     );
 
@@ -209,7 +680,7 @@ async fn test_decrement_counter() {
         &decrement_data,
         vec![
             AccountMeta::new(payer.pubkey(), true),
-            AccountMeta::new(counter_pubkey, true),
+            AccountMeta::new(counter_pubkey, false),
         ],
     );
 
@@ -223,7 +694,7 @@ async fn test_decrement_counter() {
 
     // Verify counter decremented back to 0
     let counter_account = banks_client.get_account(counter_pubkey).await.unwrap().unwrap();
-    let counter_data = CounterAccount::try_from_slice(&counter_account.data).unwrap();
+    let counter_data = CounterAccount::unpack(&counter_account.data).unwrap();
     
     assert_eq!(counter_data.count, 0);
 }
@@ -246,7 +717,7 @@ async fn test_unauthorized_access() {
 
     // Initialize counter with payer as authority
     let rent = Rent::default();
-    let account_space = std::mem::size_of::<CounterAccount>();
+    let account_space = CounterAccount::LEN;
     let rent_exemption = rent.minimum_balance(account_space);
 
     let create_account_ix = system_instruction::create_account(
@@ -281,7 +752,7 @@ async fn test_unauthorized_access() {
         &increment_data,
         vec![
             AccountMeta::new(unauthorized_user.pubkey(), true),
-            AccountMeta::new(counter_pubkey, true),
+            AccountMeta::new(counter_pubkey, false),
         ],
     );
 
@@ -294,3 +765,4078 @@ async fn test_unauthorized_access() {
     // This should fail due to authority mismatch
     assert!(banks_client.process_transaction(unauthorized_transaction).await.is_err());
 }
+
+#[tokio::test]
+async fn test_reset_counter() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+
+    // Increment a few times so there is something to reset
+    for _ in 0..3 {
+        let increment_ix = counter_ix(program_id, CounterInstruction::Increment, &authority, &counter);
+        send(&mut context, &[increment_ix], &[]).await.unwrap();
+    }
+    assert_eq!(get_counter(&mut context, counter).await.count, 3);
+
+    // Reset back to zero in a single instruction
+    let reset_ix = counter_ix(program_id, CounterInstruction::Reset, &authority, &counter);
+    send(&mut context, &[reset_ix], &[]).await.unwrap();
+
+    let counter_data = get_counter(&mut context, counter).await;
+    assert_eq!(counter_data.count, 0);
+    assert_eq!(counter_data.authority, authority);
+}
+
+#[tokio::test]
+async fn test_set_value() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+
+    // Jump straight to a known value
+    let set_ix = counter_ix(
+        program_id,
+        CounterInstruction::SetValue { value: 500 },
+        &authority,
+        &counter,
+    );
+    send(&mut context, &[set_ix], &[]).await.unwrap();
+
+    assert_eq!(get_counter(&mut context, counter).await.count, 500);
+}
+
+#[tokio::test]
+async fn test_increment_by() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+
+    let increment_ix = counter_ix(
+        program_id,
+        CounterInstruction::IncrementBy { amount: 1000 },
+        &authority,
+        &counter,
+    );
+    send(&mut context, &[increment_ix], &[]).await.unwrap();
+
+    assert_eq!(get_counter(&mut context, counter).await.count, 1000);
+}
+
+#[tokio::test]
+async fn test_increment_by_overflow() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+
+    let set_ix = counter_ix(
+        program_id,
+        CounterInstruction::SetValue { value: u64::MAX - 5 },
+        &authority,
+        &counter,
+    );
+    send(&mut context, &[set_ix], &[]).await.unwrap();
+
+    // Adding 10 would push past u64::MAX
+    let increment_ix = counter_ix(
+        program_id,
+        CounterInstruction::IncrementBy { amount: 10 },
+        &authority,
+        &counter,
+    );
+    let err = send(&mut context, &[increment_ix], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::Overflow));
+
+    assert_eq!(get_counter(&mut context, counter).await.count, u64::MAX - 5);
+}
+
+#[tokio::test]
+async fn test_increment_at_max_overflows() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+
+    let set_ix = counter_ix(
+        program_id,
+        CounterInstruction::SetValue { value: u64::MAX },
+        &authority,
+        &counter,
+    );
+    send(&mut context, &[set_ix], &[]).await.unwrap();
+
+    // A single step past u64::MAX must fail rather than wrap to 0
+    let increment_ix = instruction_builders::increment(&program_id, &authority, &counter);
+    let err = send(&mut context, &[increment_ix], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::Overflow));
+
+    let counter_data = get_counter(&mut context, counter).await;
+    assert_eq!(counter_data.count, u64::MAX);
+    assert_eq!(counter_data.update_count, 1);
+}
+
+#[tokio::test]
+async fn test_decrement_by() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+
+    let set_ix = counter_ix(
+        program_id,
+        CounterInstruction::SetValue { value: 100 },
+        &authority,
+        &counter,
+    );
+    let decrement_ix = counter_ix(
+        program_id,
+        CounterInstruction::DecrementBy { amount: 40 },
+        &authority,
+        &counter,
+    );
+    send(&mut context, &[set_ix, decrement_ix], &[]).await.unwrap();
+
+    assert_eq!(get_counter(&mut context, counter).await.count, 60);
+}
+
+#[tokio::test]
+async fn test_decrement_by_underflow() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+
+    let set_ix = counter_ix(
+        program_id,
+        CounterInstruction::SetValue { value: 5 },
+        &authority,
+        &counter,
+    );
+    send(&mut context, &[set_ix], &[]).await.unwrap();
+
+    // Subtracting more than the current count must fail instead of wrapping
+    let decrement_ix = counter_ix(
+        program_id,
+        CounterInstruction::DecrementBy { amount: 6 },
+        &authority,
+        &counter,
+    );
+    let err = send(&mut context, &[decrement_ix], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::Underflow));
+
+    assert_eq!(get_counter(&mut context, counter).await.count, 5);
+}
+
+#[tokio::test]
+async fn test_decrement_at_zero_underflows() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+
+    // A fresh counter starts at 0, so the first Decrement must fail
+    let decrement_ix = instruction_builders::decrement(&program_id, &authority, &counter);
+    let err = send(&mut context, &[decrement_ix], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::Underflow));
+    assert_ne!(err, counter_error(CounterError::Overflow));
+
+    let counter_data = get_counter(&mut context, counter).await;
+    assert_eq!(counter_data.count, 0);
+    assert_eq!(counter_data.update_count, 0);
+}
+
+#[tokio::test]
+async fn test_authority_mismatch_error_code() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let intruder = Keypair::new();
+
+    // A valid signer that is not the counter authority
+    let increment_ix = counter_ix(
+        program_id,
+        CounterInstruction::Increment,
+        &intruder.pubkey(),
+        &counter,
+    );
+    let err = send(&mut context, &[increment_ix], &[&intruder]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::Unauthorized));
+
+    assert_eq!(get_counter(&mut context, counter).await.count, 0);
+}
+
+#[tokio::test]
+async fn test_transfer_authority() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let old_authority = context.payer.pubkey();
+    let new_authority = Keypair::new();
+
+    let transfer_ix = Instruction::new_with_bytes(
+        program_id,
+        &CounterInstruction::TransferAuthority.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new_readonly(old_authority, true),
+            AccountMeta::new(counter, false),
+            AccountMeta::new_readonly(new_authority.pubkey(), false),
+        ],
+    );
+    send(&mut context, &[transfer_ix], &[]).await.unwrap();
+    assert_eq!(
+        get_counter(&mut context, counter).await.authority,
+        new_authority.pubkey()
+    );
+
+    // The old authority has lost control
+    let increment_ix = counter_ix(program_id, CounterInstruction::Increment, &old_authority, &counter);
+    let err = send(&mut context, &[increment_ix], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::Unauthorized));
+
+    // The new authority can now mutate the counter
+    let increment_ix = counter_ix(
+        program_id,
+        CounterInstruction::Increment,
+        &new_authority.pubkey(),
+        &counter,
+    );
+    send(&mut context, &[increment_ix], &[&new_authority]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 1);
+}
+
+#[tokio::test]
+async fn test_propose_and_accept_authority() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let old_authority = context.payer.pubkey();
+    let new_authority = Keypair::new();
+
+    let propose_ix = counter_ix(
+        program_id,
+        CounterInstruction::ProposeAuthority {
+            new_authority: Some(new_authority.pubkey()),
+        },
+        &old_authority,
+        &counter,
+    );
+    send(&mut context, &[propose_ix], &[]).await.unwrap();
+
+    let counter_data = get_counter(&mut context, counter).await;
+    assert_eq!(counter_data.authority, old_authority);
+    assert_eq!(counter_data.pending_authority, Some(new_authority.pubkey()));
+
+    // The original authority keeps control until the proposal is accepted
+    let increment_ix = counter_ix(program_id, CounterInstruction::Increment, &old_authority, &counter);
+    send(&mut context, &[increment_ix], &[]).await.unwrap();
+
+    let accept_ix = counter_ix(
+        program_id,
+        CounterInstruction::AcceptAuthority,
+        &new_authority.pubkey(),
+        &counter,
+    );
+    send(&mut context, &[accept_ix], &[&new_authority]).await.unwrap();
+
+    let counter_data = get_counter(&mut context, counter).await;
+    assert_eq!(counter_data.count, 1);
+    assert_eq!(counter_data.authority, new_authority.pubkey());
+    assert_eq!(counter_data.pending_authority, None);
+
+    // The old authority has lost control
+    let increment_ix = counter_ix(program_id, CounterInstruction::Increment, &old_authority, &counter);
+    let err = send(&mut context, &[increment_ix], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::Unauthorized));
+}
+
+#[tokio::test]
+async fn test_reassign_proposal_before_accept() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+    let mistaken = Keypair::new();
+    let intended = Keypair::new();
+
+    // Propose the wrong key, then overwrite the proposal
+    for proposed in [mistaken.pubkey(), intended.pubkey()] {
+        let propose_ix = counter_ix(
+            program_id,
+            CounterInstruction::ProposeAuthority {
+                new_authority: Some(proposed),
+            },
+            &authority,
+            &counter,
+        );
+        send(&mut context, &[propose_ix], &[]).await.unwrap();
+    }
+
+    // The replaced proposal can no longer be accepted
+    let accept_ix = counter_ix(
+        program_id,
+        CounterInstruction::AcceptAuthority,
+        &mistaken.pubkey(),
+        &counter,
+    );
+    let err = send(&mut context, &[accept_ix], &[&mistaken]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::Unauthorized));
+
+    let accept_ix = counter_ix(
+        program_id,
+        CounterInstruction::AcceptAuthority,
+        &intended.pubkey(),
+        &counter,
+    );
+    send(&mut context, &[accept_ix], &[&intended]).await.unwrap();
+    assert_eq!(
+        get_counter(&mut context, counter).await.authority,
+        intended.pubkey()
+    );
+}
+
+#[tokio::test]
+async fn test_clear_authority_proposal() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+    let proposed = Keypair::new();
+
+    for new_authority in [Some(proposed.pubkey()), None] {
+        let propose_ix = counter_ix(
+            program_id,
+            CounterInstruction::ProposeAuthority { new_authority },
+            &authority,
+            &counter,
+        );
+        send(&mut context, &[propose_ix], &[]).await.unwrap();
+    }
+    assert_eq!(get_counter(&mut context, counter).await.pending_authority, None);
+
+    let accept_ix = counter_ix(
+        program_id,
+        CounterInstruction::AcceptAuthority,
+        &proposed.pubkey(),
+        &counter,
+    );
+    let err = send(&mut context, &[accept_ix], &[&proposed]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::Unauthorized));
+}
+
+#[tokio::test]
+async fn test_close_counter() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+    let destination = Pubkey::new_unique();
+
+    let rent_exemption = Rent::default().minimum_balance(CounterAccount::LEN);
+    let balance_before = context.banks_client.get_balance(destination).await.unwrap();
+
+    let close_ix = Instruction::new_with_bytes(
+        program_id,
+        &CounterInstruction::CloseCounter.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new_readonly(authority, true),
+            AccountMeta::new(counter, false),
+            AccountMeta::new(destination, false),
+        ],
+    );
+    send(&mut context, &[close_ix], &[]).await.unwrap();
+
+    let balance_after = context.banks_client.get_balance(destination).await.unwrap();
+    assert_eq!(balance_after - balance_before, rent_exemption);
+    assert!(context.banks_client.get_account(counter).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_close_counter_if_equals() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+    let destination = Pubkey::new_unique();
+
+    let increment_ix = counter_ix(program_id, CounterInstruction::Increment, &authority, &counter);
+    send(&mut context, &[increment_ix], &[]).await.unwrap();
+
+    let close_ix = |expected| {
+        let mut instruction = counter_ix(
+            program_id,
+            CounterInstruction::CloseCounterIfEquals { expected },
+            &authority,
+            &counter,
+        );
+        instruction.accounts.push(AccountMeta::new(destination, false));
+        instruction
+    };
+
+    let err = send(&mut context, &[close_ix(0)], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::Conflict));
+    assert_eq!(get_counter(&mut context, counter).await.count, 1);
+
+    send(&mut context, &[close_ix(1)], &[]).await.unwrap();
+    assert!(context.banks_client.get_account(counter).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_compare_and_set() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+
+    let cas_ix = |expected, new| {
+        counter_ix(program_id, CounterInstruction::CompareAndSet { expected, new }, &authority, &counter)
+    };
+    send(&mut context, &[cas_ix(0, 5)], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 5);
+
+    // A client still believing the count is 0 loses the race
+    let err = send(&mut context, &[cas_ix(0, 9)], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::Conflict));
+    assert_eq!(get_counter(&mut context, counter).await.count, 5);
+
+    send(&mut context, &[cas_ix(5, 9)], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 9);
+}
+
+#[tokio::test]
+async fn test_last_updated_timestamp() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+
+    let increment_ix = counter_ix(program_id, CounterInstruction::Increment, &authority, &counter);
+    send(&mut context, &[increment_ix], &[]).await.unwrap();
+
+    let clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    let counter_data = get_counter(&mut context, counter).await;
+    assert_ne!(counter_data.last_updated, 0);
+    assert!(counter_data.last_updated <= clock.unix_timestamp);
+}
+
+#[tokio::test]
+async fn test_update_count_tracks_mutations() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+
+    let increment_ix = counter_ix(program_id, CounterInstruction::Increment, &authority, &counter);
+    let decrement_ix = counter_ix(program_id, CounterInstruction::Decrement, &authority, &counter);
+    send(
+        &mut context,
+        &[increment_ix.clone(), increment_ix, decrement_ix],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let counter_data = get_counter(&mut context, counter).await;
+    assert_eq!(counter_data.count, 1);
+    assert_eq!(counter_data.update_count, 3);
+}
+
+#[tokio::test]
+async fn test_max_value_ceiling() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithLimits {
+            min_value: 0,
+            max_value: 10,
+        },
+    )
+    .await;
+    let authority = context.payer.pubkey();
+    assert_eq!(get_counter(&mut context, counter).await.max_value, 10);
+
+    // Fill the counter exactly to its cap
+    let fill_ix = counter_ix(
+        program_id,
+        CounterInstruction::IncrementBy { amount: 10 },
+        &authority,
+        &counter,
+    );
+    send(&mut context, &[fill_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 10);
+
+    // One more step goes past the cap
+    let increment_ix = counter_ix(program_id, CounterInstruction::Increment, &authority, &counter);
+    let err = send(&mut context, &[increment_ix], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::AboveMaximum));
+
+    // SetValue respects the cap as well
+    let set_ix = counter_ix(
+        program_id,
+        CounterInstruction::SetValue { value: 11 },
+        &authority,
+        &counter,
+    );
+    let err = send(&mut context, &[set_ix], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::AboveMaximum));
+
+    assert_eq!(get_counter(&mut context, counter).await.count, 10);
+}
+
+#[tokio::test]
+async fn test_default_initialize_is_unbounded() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+
+    let counter_data = get_counter(&mut context, counter).await;
+    assert_eq!(counter_data.min_value, 0);
+    assert_eq!(counter_data.max_value, u64::MAX);
+}
+
+#[tokio::test]
+async fn test_min_value_floor() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithLimits {
+            min_value: 10,
+            max_value: u64::MAX,
+        },
+    )
+    .await;
+    let authority = context.payer.pubkey();
+
+    // The counter starts at its floor
+    assert_eq!(get_counter(&mut context, counter).await.count, 10);
+
+    let increment_ix = counter_ix(
+        program_id,
+        CounterInstruction::IncrementBy { amount: 2 },
+        &authority,
+        &counter,
+    );
+    let decrement_ix = counter_ix(program_id, CounterInstruction::Decrement, &authority, &counter);
+    send(
+        &mut context,
+        &[increment_ix, decrement_ix.clone(), decrement_ix.clone()],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 10);
+
+    // One more step goes below the floor
+    let err = send(&mut context, &[decrement_ix], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::BelowMinimum));
+
+    // Reset to zero would also break the floor
+    let reset_ix = counter_ix(program_id, CounterInstruction::Reset, &authority, &counter);
+    let err = send(&mut context, &[reset_ix], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::BelowMinimum));
+
+    assert_eq!(get_counter(&mut context, counter).await.count, 10);
+}
+
+#[tokio::test]
+async fn test_initialize_rejects_inverted_limits() {
+    let (program_id, mut context) = start_program().await;
+
+    let err = try_create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithLimits {
+            min_value: 5,
+            max_value: 4,
+        },
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err, counter_error_at(1, CounterError::InvalidLimits));
+}
+
+#[tokio::test]
+async fn test_configured_step() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithConfig {
+            config: CounterConfig {
+                step: 5,
+                ..CounterConfig::default()
+            },
+        },
+    )
+    .await;
+    let authority = context.payer.pubkey();
+
+    let increment_ix = counter_ix(program_id, CounterInstruction::Increment, &authority, &counter);
+    send(
+        &mut context,
+        &[increment_ix.clone(), increment_ix.clone(), increment_ix],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 15);
+
+    let decrement_ix = counter_ix(program_id, CounterInstruction::Decrement, &authority, &counter);
+    send(&mut context, &[decrement_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 10);
+}
+
+#[tokio::test]
+async fn test_initialize_rejects_zero_step() {
+    let (program_id, mut context) = start_program().await;
+
+    let err = try_create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithConfig {
+            config: CounterConfig {
+                step: 0,
+                ..CounterConfig::default()
+            },
+        },
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err, counter_error_at(1, CounterError::InvalidStep));
+}
+
+#[tokio::test]
+async fn test_saturating_mode_clamps() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithConfig {
+            config: CounterConfig {
+                mode: ArithmeticMode::Saturating,
+                ..CounterConfig::default()
+            },
+        },
+    )
+    .await;
+    let authority = context.payer.pubkey();
+
+    // Pushing past u64::MAX clamps instead of erroring
+    let set_ix = counter_ix(
+        program_id,
+        CounterInstruction::SetValue { value: u64::MAX - 1 },
+        &authority,
+        &counter,
+    );
+    let increment_ix = counter_ix(
+        program_id,
+        CounterInstruction::IncrementBy { amount: 10 },
+        &authority,
+        &counter,
+    );
+    send(&mut context, &[set_ix, increment_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, u64::MAX);
+
+    // Dropping below zero clamps as well
+    let set_ix = counter_ix(
+        program_id,
+        CounterInstruction::SetValue { value: 3 },
+        &authority,
+        &counter,
+    );
+    let decrement_ix = counter_ix(
+        program_id,
+        CounterInstruction::DecrementBy { amount: 10 },
+        &authority,
+        &counter,
+    );
+    send(&mut context, &[set_ix, decrement_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 0);
+}
+
+#[tokio::test]
+async fn test_default_mode_is_checked() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+
+    assert_eq!(
+        get_counter(&mut context, counter).await.mode,
+        ArithmeticMode::Checked
+    );
+}
+
+#[tokio::test]
+async fn test_wrapping_mode_wraps_to_zero() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithConfig {
+            config: CounterConfig {
+                mode: ArithmeticMode::Wrapping,
+                ..CounterConfig::default()
+            },
+        },
+    )
+    .await;
+    let authority = context.payer.pubkey();
+
+    let set_ix = counter_ix(
+        program_id,
+        CounterInstruction::SetValue { value: u64::MAX },
+        &authority,
+        &counter,
+    );
+    let increment_ix = counter_ix(program_id, CounterInstruction::Increment, &authority, &counter);
+    send(&mut context, &[set_ix, increment_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 0);
+
+    // And back the other way
+    let decrement_ix = counter_ix(program_id, CounterInstruction::Decrement, &authority, &counter);
+    send(&mut context, &[decrement_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, u64::MAX);
+}
+
+#[tokio::test]
+async fn test_initialize_pda_counter() {
+    let (program_id, mut context) = start_program().await;
+    let authority = context.payer.pubkey();
+
+    let (initialize_ix, counter, bump) = initialize_pda_ix(program_id, &authority, "scores");
+    send(&mut context, &[initialize_ix], &[]).await.unwrap();
+
+    let counter_data = get_counter(&mut context, counter).await;
+    assert_eq!(counter_data.count, 0);
+    assert_eq!(counter_data.authority, authority);
+    assert_eq!(counter_data.bump, bump);
+    assert_eq!(
+        counter_data.pda_seeds,
+        Some(PdaSeeds {
+            authority,
+            seed: b"scores".to_vec(),
+        })
+    );
+
+    // The PDA counter behaves like any other counter
+    let increment_ix = counter_ix(program_id, CounterInstruction::Increment, &authority, &counter);
+    send(&mut context, &[increment_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 1);
+}
+
+#[tokio::test]
+async fn test_initialize_pda_rejects_wrong_address() {
+    let (program_id, mut context) = start_program().await;
+    let authority = context.payer.pubkey();
+
+    // Point the instruction at a PDA derived from a different seed
+    let (mut initialize_ix, _, _) = initialize_pda_ix(program_id, &authority, "scores");
+    let (_, other_counter, _) = initialize_pda_ix(program_id, &authority, "other");
+    initialize_ix.accounts[1].pubkey = other_counter;
+
+    let err = send(&mut context, &[initialize_ix], &[]).await.unwrap_err();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(0, InstructionError::InvalidSeeds)
+    );
+}
+
+#[tokio::test]
+async fn test_pda_counter_rejects_substituted_account() {
+    let (program_id, mut context) = start_program().await;
+    let authority = context.payer.pubkey();
+
+    let (initialize_ix, counter, _) = initialize_pda_ix(program_id, &authority, "scores");
+    send(&mut context, &[initialize_ix], &[]).await.unwrap();
+
+    // Plant a byte-for-byte copy of the PDA counter at an unrelated address
+    let look_alike = Pubkey::new_unique();
+    let account = context.banks_client.get_account(counter).await.unwrap().unwrap();
+    context.set_account(&look_alike, &account.into());
+
+    let increment_ix = counter_ix(program_id, CounterInstruction::Increment, &authority, &look_alike);
+    let err = send(&mut context, &[increment_ix], &[]).await.unwrap_err();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(0, InstructionError::InvalidSeeds)
+    );
+
+    // The real counter still works
+    let increment_ix = counter_ix(program_id, CounterInstruction::Increment, &authority, &counter);
+    send(&mut context, &[increment_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 1);
+}
+
+#[tokio::test]
+async fn test_multisig_two_of_three_passes() {
+    let (program_id, mut context) = start_program().await;
+    let (counter, members) = create_multisig_counter(&mut context, program_id).await;
+
+    let mut increment_ix = counter_ix(
+        program_id,
+        CounterInstruction::Increment,
+        &members[0].pubkey(),
+        &counter,
+    );
+    increment_ix
+        .accounts
+        .push(AccountMeta::new_readonly(members[2].pubkey(), true));
+    send(&mut context, &[increment_ix], &[&members[0], &members[2]])
+        .await
+        .unwrap();
+
+    assert_eq!(get_counter(&mut context, counter).await.count, 1);
+}
+
+#[tokio::test]
+async fn test_multisig_one_of_three_fails() {
+    let (program_id, mut context) = start_program().await;
+    let (counter, members) = create_multisig_counter(&mut context, program_id).await;
+
+    let increment_ix = counter_ix(
+        program_id,
+        CounterInstruction::Increment,
+        &members[0].pubkey(),
+        &counter,
+    );
+    let err = send(&mut context, &[increment_ix], &[&members[0]]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::NotEnoughSigners));
+
+    // The single authority no longer suffices either
+    let authority = context.payer.pubkey();
+    let increment_ix = counter_ix(program_id, CounterInstruction::Increment, &authority, &counter);
+    let err = send(&mut context, &[increment_ix], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::NotEnoughSigners));
+
+    assert_eq!(get_counter(&mut context, counter).await.count, 0);
+}
+
+#[tokio::test]
+async fn test_set_multisig_rejects_unreachable_threshold() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+
+    let set_multisig_ix = counter_ix(
+        program_id,
+        CounterInstruction::SetMultisig {
+            multisig: Some(MultisigConfig {
+                signers: vec![Pubkey::new_unique()],
+                threshold: 2,
+            }),
+        },
+        &authority,
+        &counter,
+    );
+    let err = send(&mut context, &[set_multisig_ix], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::InvalidMultisig));
+}
+
+#[tokio::test]
+async fn test_delegate_can_increment_but_not_transfer() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+    let delegate = Keypair::new();
+
+    let set_delegate_ix = counter_ix(
+        program_id,
+        CounterInstruction::SetDelegate {
+            delegate: delegate.pubkey(),
+        },
+        &authority,
+        &counter,
+    );
+    send(&mut context, &[set_delegate_ix], &[]).await.unwrap();
+
+    let increment_ix = counter_ix(
+        program_id,
+        CounterInstruction::Increment,
+        &delegate.pubkey(),
+        &counter,
+    );
+    send(&mut context, &[increment_ix], &[&delegate]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 1);
+
+    // Authority management stays with the authority
+    let transfer_ix = Instruction::new_with_bytes(
+        program_id,
+        &CounterInstruction::TransferAuthority.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new_readonly(delegate.pubkey(), true),
+            AccountMeta::new(counter, false),
+            AccountMeta::new_readonly(delegate.pubkey(), false),
+        ],
+    );
+    let err = send(&mut context, &[transfer_ix], &[&delegate]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::Unauthorized));
+    assert_eq!(get_counter(&mut context, counter).await.authority, authority);
+}
+
+#[tokio::test]
+async fn test_revoked_delegate_loses_access() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+    let delegate = Keypair::new();
+
+    let set_delegate_ix = counter_ix(
+        program_id,
+        CounterInstruction::SetDelegate {
+            delegate: delegate.pubkey(),
+        },
+        &authority,
+        &counter,
+    );
+    let revoke_ix = counter_ix(program_id, CounterInstruction::RevokeDelegate, &authority, &counter);
+    send(&mut context, &[set_delegate_ix, revoke_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.delegate, None);
+
+    let increment_ix = counter_ix(
+        program_id,
+        CounterInstruction::Increment,
+        &delegate.pubkey(),
+        &counter,
+    );
+    let err = send(&mut context, &[increment_ix], &[&delegate]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::Unauthorized));
+}
+
+#[tokio::test]
+async fn test_mutations_emit_events() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+
+    let increment_ix = counter_ix(
+        program_id,
+        CounterInstruction::IncrementBy { amount: 5 },
+        &authority,
+        &counter,
+    );
+    let decrement_ix = instruction_builders::decrement(&program_id, &authority, &counter);
+    let logs = send_with_logs(&mut context, &[increment_ix, decrement_ix], &[]).await;
+
+    assert_eq!(
+        extract_events(&logs),
+        vec![
+            CounterEvent::Incremented { counter, count: 5 },
+            CounterEvent::Decremented { counter, count: 4 },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_initialize_emits_event() {
+    let (program_id, mut context) = start_program().await;
+    let authority = context.payer.pubkey();
+
+    let (initialize_ix, counter, _) = initialize_pda_ix(program_id, &authority, "events");
+    let logs = send_with_logs(&mut context, &[initialize_ix], &[]).await;
+
+    assert_eq!(
+        extract_events(&logs),
+        vec![CounterEvent::Initialized { counter, count: 0, nonce: 0 }]
+    );
+}
+
+#[tokio::test]
+async fn test_milestone_crossing_invokes_target_once() {
+    let target = Pubkey::new_unique();
+    let mut program_test = ProgramTest::default();
+    program_test.add_program("milestone_target", target, processor!(record_milestone));
+    let (program_id, mut context) = start_program_with(program_test).await;
+    let authority = context.payer.pubkey();
+
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithConfig {
+            config: CounterConfig {
+                milestone: Some(3),
+                milestone_target: Some(target),
+                ..CounterConfig::default()
+            },
+        },
+    )
+    .await;
+    let increment_ix = |amount| {
+        let mut instruction = counter_ix(
+            program_id,
+            CounterInstruction::IncrementBy { amount },
+            &authority,
+            &counter,
+        );
+        instruction.accounts.push(AccountMeta::new_readonly(target, false));
+        instruction
+    };
+    let callback_log = |count: u64| format!("Program log: Milestone callback: {} reached {}", counter, count);
+
+    // Below the milestone: no callback
+    let logs = send_with_logs(&mut context, &[increment_ix(2)], &[]).await;
+    assert!(!logs.iter().any(|line| line.contains("Milestone callback")));
+    assert!(!get_counter(&mut context, counter).await.milestone_fired);
+
+    // Crossing the milestone notifies the target with the new count
+    let logs = send_with_logs(&mut context, &[increment_ix(2)], &[]).await;
+    assert!(logs.contains(&callback_log(4)));
+    assert!(get_counter(&mut context, counter).await.milestone_fired);
+
+    // Dropping back and crossing again does not fire a second time
+    let decrement_ix = counter_ix(
+        program_id,
+        CounterInstruction::DecrementBy { amount: 3 },
+        &authority,
+        &counter,
+    );
+    send(&mut context, &[decrement_ix], &[]).await.unwrap();
+    let logs = send_with_logs(&mut context, &[increment_ix(5)], &[]).await;
+    assert!(!logs.iter().any(|line| line.contains("Milestone callback")));
+    assert_eq!(get_counter(&mut context, counter).await.count, 6);
+}
+
+#[tokio::test]
+async fn test_milestone_without_target_rejected() {
+    let (program_id, mut context) = start_program().await;
+
+    let err = try_create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithConfig {
+            config: CounterConfig {
+                milestone: Some(3),
+                ..CounterConfig::default()
+            },
+        },
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err, counter_error_at(1, CounterError::InvalidMilestone));
+}
+
+#[tokio::test]
+async fn test_signed_counter_goes_negative() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter_with(&mut context, program_id, CounterInstruction::InitializeSigned).await;
+    let authority = context.payer.pubkey();
+
+    let counter_data = get_counter(&mut context, counter).await;
+    assert_eq!(counter_data.kind, CounterKind::Signed);
+    assert_eq!(counter_data.signed_count(), 0);
+
+    let decrement_ix = counter_ix(program_id, CounterInstruction::Decrement, &authority, &counter);
+    let decrement_by_ix = counter_ix(
+        program_id,
+        CounterInstruction::DecrementBy { amount: 5 },
+        &authority,
+        &counter,
+    );
+    send(&mut context, &[decrement_ix, decrement_by_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.signed_count(), -6);
+
+    let increment_ix = counter_ix(
+        program_id,
+        CounterInstruction::IncrementBy { amount: 10 },
+        &authority,
+        &counter,
+    );
+    send(&mut context, &[increment_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.signed_count(), 4);
+}
+
+#[tokio::test]
+async fn test_signed_counter_detects_overflow_and_underflow() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter_with(&mut context, program_id, CounterInstruction::InitializeSigned).await;
+    let authority = context.payer.pubkey();
+
+    // Past i64::MAX
+    let set_max_ix = counter_ix(
+        program_id,
+        CounterInstruction::SetValue { value: i64::MAX as u64 },
+        &authority,
+        &counter,
+    );
+    let increment_ix = counter_ix(program_id, CounterInstruction::Increment, &authority, &counter);
+    let err = send(&mut context, &[set_max_ix, increment_ix], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error_at(1, CounterError::Overflow));
+
+    // Below i64::MIN
+    let set_min_ix = counter_ix(
+        program_id,
+        CounterInstruction::SetValue { value: i64::MIN as u64 },
+        &authority,
+        &counter,
+    );
+    let decrement_ix = counter_ix(program_id, CounterInstruction::Decrement, &authority, &counter);
+    let err = send(&mut context, &[set_min_ix, decrement_ix], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error_at(1, CounterError::Underflow));
+
+    // Amounts that do not fit an i64
+    let increment_by_ix = counter_ix(
+        program_id,
+        CounterInstruction::IncrementBy { amount: u64::MAX },
+        &authority,
+        &counter,
+    );
+    let err = send(&mut context, &[increment_by_ix], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::Overflow));
+    assert_eq!(get_counter(&mut context, counter).await.signed_count(), 0);
+}
+
+#[tokio::test]
+async fn test_batch_applies_net_delta() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+
+    let batch_ix = counter_ix(
+        program_id,
+        CounterInstruction::Batch { ops: vec![5, 3, -2] },
+        &authority,
+        &counter,
+    );
+    send(&mut context, &[batch_ix], &[]).await.unwrap();
+
+    let counter_data = get_counter(&mut context, counter).await;
+    assert_eq!(counter_data.count, 6);
+    assert_eq!(counter_data.update_count, 1);
+}
+
+#[tokio::test]
+async fn test_batch_underflow_aborts_whole_batch() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+
+    let batch_ix = counter_ix(
+        program_id,
+        CounterInstruction::Batch { ops: vec![5, -10, 20] },
+        &authority,
+        &counter,
+    );
+    let err = send(&mut context, &[batch_ix], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::Underflow));
+
+    let counter_data = get_counter(&mut context, counter).await;
+    assert_eq!(counter_data.count, 0);
+    assert_eq!(counter_data.update_count, 0);
+}
+
+#[tokio::test]
+async fn test_named_counters_update_independently() {
+    let (program_id, mut context) = start_program().await;
+    let multi = create_multi_counter(&mut context, program_id, 2).await;
+    let authority = context.payer.pubkey();
+
+    let named_ix = |instruction| counter_ix(program_id, instruction, &authority, &multi);
+    send(
+        &mut context,
+        &[
+            named_ix(CounterInstruction::AddNamedCounter { name: "wins".to_string() }),
+            named_ix(CounterInstruction::AddNamedCounter { name: "losses".to_string() }),
+        ],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    send(
+        &mut context,
+        &[
+            named_ix(CounterInstruction::IncrementNamed { name: "wins".to_string() }),
+            named_ix(CounterInstruction::IncrementNamed { name: "wins".to_string() }),
+            named_ix(CounterInstruction::IncrementNamed { name: "losses".to_string() }),
+            named_ix(CounterInstruction::DecrementNamed { name: "wins".to_string() }),
+        ],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let multi_data = get_multi_counter(&mut context, multi).await;
+    assert_eq!(multi_data.authority, authority);
+    assert_eq!(multi_data.get("wins"), Some(1));
+    assert_eq!(multi_data.get("losses"), Some(1));
+}
+
+#[tokio::test]
+async fn test_named_counter_errors() {
+    let (program_id, mut context) = start_program().await;
+    let multi = create_multi_counter(&mut context, program_id, 1).await;
+    let authority = context.payer.pubkey();
+
+    let named_ix = |instruction| counter_ix(program_id, instruction, &authority, &multi);
+
+    let err = send(
+        &mut context,
+        &[named_ix(CounterInstruction::IncrementNamed { name: "missing".to_string() })],
+        &[],
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err, counter_error(CounterError::NotFound));
+
+    let add_ix = || named_ix(CounterInstruction::AddNamedCounter { name: "score".to_string() });
+    send(&mut context, &[add_ix()], &[]).await.unwrap();
+    let err = send(&mut context, &[add_ix()], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::DuplicateName));
+
+    let err = send(
+        &mut context,
+        &[named_ix(CounterInstruction::DecrementNamed { name: "score".to_string() })],
+        &[],
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err, counter_error(CounterError::Underflow));
+}
+
+#[tokio::test]
+async fn test_adding_named_counters_grows_account() {
+    let (program_id, mut context) = start_program().await;
+    let multi = create_multi_counter(&mut context, program_id, 1).await;
+    let authority = context.payer.pubkey();
+    let initial_len = MultiCounterAccount::space(1);
+
+    let named_ix = |instruction| counter_ix(program_id, instruction, &authority, &multi);
+    let add_ix = |name: &str| {
+        let mut instruction = named_ix(CounterInstruction::AddNamedCounter { name: name.to_string() });
+        instruction.accounts.push(AccountMeta::new(authority, true));
+        instruction.accounts.push(AccountMeta::new_readonly(system_program::id(), false));
+        instruction
+    };
+    let names = ["first", "second-with-a-longer-name", "third-uses-the-longest-name-here"];
+    send(&mut context, &names.map(add_ix), &[]).await.unwrap();
+
+    let account = context.banks_client.get_account(multi).await.unwrap().unwrap();
+    assert!(account.data.len() > initial_len);
+    assert!(Rent::default().is_exempt(account.lamports, account.data.len()));
+
+    send(
+        &mut context,
+        &[named_ix(CounterInstruction::IncrementNamed { name: names[1].to_string() })],
+        &[],
+    )
+    .await
+    .unwrap();
+    let multi_data = get_multi_counter(&mut context, multi).await;
+    let stored: Vec<&str> = multi_data.counters.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(stored, names);
+    assert_eq!(multi_data.get(names[0]), Some(0));
+    assert_eq!(multi_data.get(names[1]), Some(1));
+    assert_eq!(multi_data.get(names[2]), Some(0));
+}
+
+#[tokio::test]
+async fn test_growing_without_payer_fails() {
+    let (program_id, mut context) = start_program().await;
+    let multi = create_multi_counter(&mut context, program_id, 1).await;
+    let authority = context.payer.pubkey();
+
+    let add_ix = |name: &str| {
+        counter_ix(
+            program_id,
+            CounterInstruction::AddNamedCounter { name: name.to_string() },
+            &authority,
+            &multi,
+        )
+    };
+    // The first counter fills the account exactly
+    send(&mut context, &[add_ix(&"x".repeat(MultiCounterAccount::MAX_NAME_LEN))], &[])
+        .await
+        .unwrap();
+    let err = send(&mut context, &[add_ix("overflows")], &[]).await.unwrap_err();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(0, InstructionError::NotEnoughAccountKeys)
+    );
+}
+
+#[test]
+fn test_decode_counter_round_trip() {
+    let counter_data = CounterAccount {
+        count: CounterValue(42),
+        min_value: CounterValue(10),
+        max_value: CounterValue(100),
+        step: 2,
+        mode: ArithmeticMode::Saturating,
+        pending_authority: Some(Pubkey::new_unique()),
+        delegate: Some(Pubkey::new_unique()),
+        previous_count: Some(CounterValue(40)),
+        ..sample_counter(Pubkey::new_unique())
+    };
+
+    // Stored the way the program does: at the front of a full size account
+    let mut data = vec![0; CounterAccount::LEN];
+    counter_data.serialize(&mut &mut data[..]).unwrap();
+    assert_eq!(decode_counter(&data).unwrap(), counter_data);
+
+    assert_eq!(
+        decode_counter(&[0; CounterAccount::LEN]).unwrap_err(),
+        ProgramError::from(CounterError::NotInitialized)
+    );
+    let truncated = [&COUNTER_DISCRIMINATOR[..], &[CounterAccount::VERSION, 2, 3]].concat();
+    assert_eq!(decode_counter(&truncated).unwrap_err(), ProgramError::InvalidAccountData);
+}
+
+#[tokio::test]
+async fn test_count_of_reads_fetched_account() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+
+    let increment_ix = instruction_builders::increment(&program_id, &authority, &counter);
+    send(&mut context, &[increment_ix], &[]).await.unwrap();
+
+    let account = context.banks_client.get_account(counter).await.unwrap().unwrap();
+    assert_eq!(count_of(&account).unwrap(), 1);
+}
+
+#[tokio::test]
+async fn test_rate_limit_allows_one_change_per_slot() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithConfig {
+            config: CounterConfig {
+                rate_limited: true,
+                ..CounterConfig::default()
+            },
+        },
+    )
+    .await;
+    let authority = context.payer.pubkey();
+    let increment_ix = || instruction_builders::increment(&program_id, &authority, &counter);
+
+    // Both instructions of one transaction run in the same slot
+    let err = send(&mut context, &[increment_ix(), increment_ix()], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error_at(1, CounterError::RateLimited));
+
+    send(&mut context, &[increment_ix()], &[]).await.unwrap();
+    let counter_data = get_counter(&mut context, counter).await;
+    assert_eq!(counter_data.count, 1);
+
+    // A later slot may change it again
+    context.warp_to_slot(counter_data.last_slot + 1).unwrap();
+    let decrement_ix = instruction_builders::decrement(&program_id, &authority, &counter);
+    send(&mut context, &[decrement_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 0);
+}
+
+#[tokio::test]
+async fn test_cooldown_expires_after_clock_advances() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithConfig {
+            config: CounterConfig {
+                cooldown_secs: 60,
+                ..CounterConfig::default()
+            },
+        },
+    )
+    .await;
+    let authority = context.payer.pubkey();
+    let increment_ix = || instruction_builders::increment(&program_id, &authority, &counter);
+
+    // Creation starts the first cooldown
+    let err = send(&mut context, &[increment_ix()], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::CooldownActive));
+
+    advance_clock(&mut context, 30).await;
+    let err = send(&mut context, &[increment_ix()], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::CooldownActive));
+
+    advance_clock(&mut context, 30).await;
+    send(&mut context, &[increment_ix()], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 1);
+
+    // The change restarts the cooldown
+    let err = send(&mut context, &[increment_ix()], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::CooldownActive));
+}
+
+#[tokio::test]
+async fn test_negative_cooldown_rejected() {
+    let (program_id, mut context) = start_program().await;
+
+    let err = try_create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithConfig {
+            config: CounterConfig {
+                cooldown_secs: -1,
+                ..CounterConfig::default()
+            },
+        },
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err, counter_error_at(1, CounterError::InvalidCooldown));
+}
+
+#[tokio::test]
+async fn test_history_keeps_most_recent_values() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+    let increment_ix = || instruction_builders::increment(&program_id, &authority, &counter);
+
+    send(&mut context, &[increment_ix(), increment_ix()], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.history_in_order(), vec![1, 2]);
+
+    let increments: Vec<Instruction> = (0..8).map(|_| increment_ix()).collect();
+    send(&mut context, &increments, &[]).await.unwrap();
+
+    let counter_data = get_counter(&mut context, counter).await;
+    assert_eq!(counter_data.count, 10);
+    assert_eq!(counter_data.history_in_order(), (3..=10).collect::<Vec<u64>>());
+}
+
+#[tokio::test]
+async fn test_undo_reverts_increment_once() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+
+    let increment_ix = counter_ix(
+        program_id,
+        CounterInstruction::IncrementBy { amount: 3 },
+        &authority,
+        &counter,
+    );
+    let undo_ix = || counter_ix(program_id, CounterInstruction::Undo, &authority, &counter);
+    send(&mut context, &[increment_ix.clone(), increment_ix, undo_ix()], &[]).await.unwrap();
+
+    let counter_data = get_counter(&mut context, counter).await;
+    assert_eq!(counter_data.count, 3);
+    assert_eq!(counter_data.last_delta, 0);
+
+    // Undo can't be chained
+    let err = send(&mut context, &[undo_ix()], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::NothingToUndo));
+    assert_eq!(get_counter(&mut context, counter).await.count, 3);
+}
+
+#[tokio::test]
+async fn test_undo_restores_value_before_set() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+
+    let decrement_ix = instruction_builders::decrement(&program_id, &authority, &counter);
+    let set_value_ix = |value| counter_ix(program_id, CounterInstruction::SetValue { value }, &authority, &counter);
+    let undo_ix = counter_ix(program_id, CounterInstruction::Undo, &authority, &counter);
+    send(&mut context, &[set_value_ix(10), decrement_ix], &[]).await.unwrap();
+    send(&mut context, &[set_value_ix(u64::MAX), undo_ix.clone()], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 9);
+
+    // Undoing a decrement adds the amount back
+    let decrement_by_ix = counter_ix(
+        program_id,
+        CounterInstruction::DecrementBy { amount: 4 },
+        &authority,
+        &counter,
+    );
+    send(&mut context, &[decrement_by_ix, undo_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 9);
+}
+
+#[tokio::test]
+async fn test_frozen_counter_rejects_every_change() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+
+    let increment_ix = instruction_builders::increment(&program_id, &authority, &counter);
+    let freeze_ix = counter_ix(program_id, CounterInstruction::Freeze, &authority, &counter);
+    send(&mut context, &[increment_ix, freeze_ix], &[]).await.unwrap();
+
+    let new_authority = Pubkey::new_unique();
+    let mut transfer_ix = counter_ix(program_id, CounterInstruction::TransferAuthority, &authority, &counter);
+    transfer_ix.accounts.push(AccountMeta::new_readonly(new_authority, false));
+    let mutations = vec![
+        instruction_builders::increment(&program_id, &authority, &counter),
+        instruction_builders::decrement(&program_id, &authority, &counter),
+        counter_ix(program_id, CounterInstruction::SetValue { value: 7 }, &authority, &counter),
+        counter_ix(program_id, CounterInstruction::Reset, &authority, &counter),
+        counter_ix(program_id, CounterInstruction::Batch { ops: vec![1] }, &authority, &counter),
+        transfer_ix,
+        counter_ix(program_id, CounterInstruction::Freeze, &authority, &counter),
+    ];
+    for mutation in mutations {
+        let err = send(&mut context, &[mutation], &[]).await.unwrap_err();
+        assert_eq!(err, counter_error(CounterError::Frozen));
+    }
+
+    // Reads still work
+    let counter_data = get_counter(&mut context, counter).await;
+    assert!(counter_data.frozen);
+    assert_eq!(counter_data.count, 1);
+    assert_eq!(counter_data.authority, authority);
+}
+
+#[tokio::test]
+async fn test_only_authority_can_freeze() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let intruder = Keypair::new();
+
+    let freeze_ix = counter_ix(program_id, CounterInstruction::Freeze, &intruder.pubkey(), &counter);
+    let err = send(&mut context, &[freeze_ix], &[&intruder]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::Unauthorized));
+    assert!(!get_counter(&mut context, counter).await.frozen);
+}
+
+#[tokio::test]
+async fn test_pause_blocks_count_changes_until_unpaused() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+
+    let pause_ix = counter_ix(program_id, CounterInstruction::Pause, &authority, &counter);
+    send(&mut context, &[pause_ix], &[]).await.unwrap();
+
+    let paused_mutations = vec![
+        instruction_builders::increment(&program_id, &authority, &counter),
+        instruction_builders::decrement(&program_id, &authority, &counter),
+        counter_ix(program_id, CounterInstruction::SetValue { value: 7 }, &authority, &counter),
+    ];
+    for mutation in paused_mutations {
+        let err = send(&mut context, &[mutation], &[]).await.unwrap_err();
+        assert_eq!(err, counter_error(CounterError::Paused));
+    }
+
+    // Authority management still works while paused
+    let new_authority = Keypair::new();
+    let mut transfer_ix = counter_ix(program_id, CounterInstruction::TransferAuthority, &authority, &counter);
+    transfer_ix.accounts.push(AccountMeta::new_readonly(new_authority.pubkey(), false));
+    send(&mut context, &[transfer_ix], &[]).await.unwrap();
+
+    let new_authority_key = new_authority.pubkey();
+    let unpause_ix = counter_ix(program_id, CounterInstruction::Unpause, &new_authority_key, &counter);
+    let increment_ix = instruction_builders::increment(&program_id, &new_authority_key, &counter);
+    send(&mut context, &[unpause_ix, increment_ix], &[&new_authority]).await.unwrap();
+
+    let counter_data = get_counter(&mut context, counter).await;
+    assert!(!counter_data.paused);
+    assert_eq!(counter_data.count, 1);
+}
+
+#[tokio::test]
+async fn test_initialize_with_separate_authority() {
+    let (program_id, mut context) = start_program().await;
+    let payer = context.payer.pubkey();
+    let owner = Keypair::new();
+
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithAuthority {
+            authority: owner.pubkey(),
+        },
+    )
+    .await;
+    assert_eq!(get_counter(&mut context, counter).await.authority, owner.pubkey());
+
+    // The designated authority can increment while the payer covers fees
+    let increment_ix = instruction_builders::increment(&program_id, &owner.pubkey(), &counter);
+    send(&mut context, &[increment_ix], &[&owner]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 1);
+
+    // The payer has no control over the counter
+    let increment_ix = instruction_builders::increment(&program_id, &payer, &counter);
+    let err = send(&mut context, &[increment_ix], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::Unauthorized));
+}
+
+#[tokio::test]
+async fn test_initialize_with_value() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithValue { initial: 42 },
+    )
+    .await;
+    assert_eq!(get_counter(&mut context, counter).await.count, 42);
+
+    // A live counter can't be re-seeded
+    let authority = context.payer.pubkey();
+    let reinitialize_ix = counter_ix(
+        program_id,
+        CounterInstruction::InitializeWithValue { initial: 7 },
+        &authority,
+        &counter,
+    );
+    let err = send(&mut context, &[reinitialize_ix], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::AlreadyInitialized));
+    assert_eq!(get_counter(&mut context, counter).await.count, 42);
+}
+
+#[tokio::test]
+async fn test_initial_value_outside_limits_rejected() {
+    let (program_id, mut context) = start_program().await;
+
+    let err = try_create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithConfig {
+            config: CounterConfig {
+                max_value: 10,
+                initial_value: Some(11),
+                ..CounterConfig::default()
+            },
+        },
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err, counter_error_at(1, CounterError::AboveMaximum));
+}
+
+#[tokio::test]
+async fn test_trailing_instruction_bytes_rejected() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+
+    let mut increment_ix = instruction_builders::increment(&program_id, &authority, &counter);
+    increment_ix.data.extend_from_slice(&[0, 0, 0]);
+    let mut increment_by_ix = counter_ix(
+        program_id,
+        CounterInstruction::IncrementBy { amount: 1 },
+        &authority,
+        &counter,
+    );
+    increment_by_ix.data.push(1);
+
+    for instruction in [increment_ix, increment_by_ix] {
+        let err = send(&mut context, &[instruction], &[]).await.unwrap_err();
+        assert_eq!(
+            err,
+            TransactionError::InstructionError(0, InstructionError::InvalidInstructionData)
+        );
+    }
+    assert_eq!(get_counter(&mut context, counter).await.count, 0);
+}
+
+#[cfg(feature = "cli")]
+#[test]
+fn test_cli_parses_subcommands() {
+    use simple_solana_program::cli;
+
+    let program_id = Pubkey::new_unique().to_string();
+    let counter = Pubkey::new_unique().to_string();
+
+    let matches = cli::command()
+        .try_get_matches_from(["counter-cli", "--program-id", &program_id, "init"])
+        .unwrap();
+    assert_eq!(matches.subcommand_name(), Some("init"));
+    assert_eq!(matches.get_one::<String>("url").unwrap(), cli::DEFAULT_URL);
+
+    for subcommand in ["increment", "decrement", "get"] {
+        let matches = cli::command()
+            .try_get_matches_from([
+                "counter-cli",
+                "--url",
+                "http://127.0.0.1:8899",
+                "--keypair",
+                "id.json",
+                "--program-id",
+                &program_id,
+                subcommand,
+                &counter,
+            ])
+            .unwrap();
+        let (name, sub_matches) = matches.subcommand().unwrap();
+        assert_eq!(name, subcommand);
+        assert_eq!(sub_matches.get_one::<String>("counter"), Some(&counter));
+    }
+
+    // Counter operations need an address, and a subcommand is required
+    assert!(cli::command()
+        .try_get_matches_from(["counter-cli", "--program-id", &program_id, "increment"])
+        .is_err());
+    assert!(cli::command()
+        .try_get_matches_from(["counter-cli", "--program-id", &program_id])
+        .is_err());
+}
+
+#[cfg(feature = "schema")]
+#[test]
+fn test_write_schema_describes_counter_fields() {
+    use borsh::schema::{BorshSchemaContainer, Definition, Fields};
+    use simple_solana_program::write_schema;
+
+    let path = std::env::temp_dir().join(format!("counter-schema-{}.borsh", std::process::id()));
+    write_schema(&path).unwrap();
+    let containers = Vec::<BorshSchemaContainer>::try_from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let account = &containers[0];
+    assert_eq!(account.declaration, "CounterAccount");
+    let fields = match &account.definitions[&account.declaration] {
+        Definition::Struct {
+            fields: Fields::NamedFields(fields),
+        } => fields,
+        other => panic!("unexpected CounterAccount definition {:?}", other),
+    };
+    assert!(fields.contains(&("count".to_string(), "u64".to_string())));
+    assert!(fields.contains(&("authority".to_string(), "Pubkey".to_string())));
+
+    assert_eq!(containers[1].declaration, "CounterInstruction");
+}
+
+#[tokio::test]
+async fn test_stored_current_version_counter_is_read() {
+    let (program_id, mut context) = start_program().await;
+    let authority = context.payer.pubkey();
+
+    let mut data = vec![0; CounterAccount::LEN];
+    CounterAccount {
+        count: CounterValue(9),
+        ..sample_counter(authority)
+    }
+    .serialize(&mut &mut data[..])
+    .unwrap();
+    assert_eq!(data[..8], COUNTER_DISCRIMINATOR);
+    assert_eq!(data[8], 16);
+    let counter = store_counter_data(&mut context, program_id, data);
+
+    let increment_ix = instruction_builders::increment(&program_id, &authority, &counter);
+    send(&mut context, &[increment_ix], &[]).await.unwrap();
+
+    let counter_data = get_counter(&mut context, counter).await;
+    assert_eq!(counter_data.version, CounterAccount::VERSION);
+    assert_eq!(counter_data.count, 10);
+}
+
+#[tokio::test]
+async fn test_unknown_version_rejected() {
+    let (program_id, mut context) = start_program().await;
+    let authority = context.payer.pubkey();
+
+    let mut data = vec![0; CounterAccount::LEN];
+    sample_counter(authority).serialize(&mut &mut data[..]).unwrap();
+    data[8] = 0xEE;
+    let counter = store_counter_data(&mut context, program_id, data.clone());
+
+    let increment_ix = instruction_builders::increment(&program_id, &authority, &counter);
+    let err = send(&mut context, &[increment_ix], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::UnsupportedVersion));
+    assert_eq!(
+        decode_counter(&data).unwrap_err(),
+        ProgramError::from(CounterError::UnsupportedVersion)
+    );
+
+    // Nor can it be migrated: only the original layout is upgraded
+    let migrate_ix = counter_ix(program_id, CounterInstruction::Migrate, &authority, &counter);
+    let err = send(&mut context, &[migrate_ix], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::UnsupportedVersion));
+}
+
+#[tokio::test]
+async fn test_migrate_baseline_counter_to_current() {
+    let (program_id, mut context) = start_program().await;
+    let authority = context.payer.pubkey();
+
+    // The original layout was a bare count and authority; a small count's low
+    // byte must not be taken for a version
+    let mut data = 3u64.to_le_bytes().to_vec();
+    data.extend_from_slice(authority.as_ref());
+    assert_eq!(data.len(), CounterAccount::BASELINE_LEN);
+    assert_eq!(CounterAccount::stored_version(&data).unwrap(), CounterAccount::BASELINE_VERSION);
+    let counter = store_counter_data(&mut context, program_id, data);
+
+    let increment_ix = instruction_builders::increment(&program_id, &authority, &counter);
+    // Until migrated the account is still sized for the original layout
+    let err = send(&mut context, std::slice::from_ref(&increment_ix), &[]).await.unwrap_err();
+    assert_eq!(err, TransactionError::InstructionError(0, InstructionError::AccountDataTooSmall));
+
+    let mut migrate_ix = counter_ix(program_id, CounterInstruction::Migrate, &authority, &counter);
+    migrate_ix.accounts.push(AccountMeta::new(authority, true));
+    migrate_ix.accounts.push(AccountMeta::new_readonly(system_program::id(), false));
+    send(&mut context, std::slice::from_ref(&migrate_ix), &[]).await.unwrap();
+
+    let account = context.banks_client.get_account(counter).await.unwrap().unwrap();
+    assert_eq!(account.data.len(), CounterAccount::LEN);
+    assert!(Rent::default().is_exempt(account.lamports, account.data.len()));
+
+    let counter_data = get_counter(&mut context, counter).await;
+    assert_eq!(counter_data.version, CounterAccount::VERSION);
+    assert_eq!((counter_data.count, counter_data.authority), (CounterValue(3), authority));
+    assert_eq!((counter_data.min_value, counter_data.max_value), (CounterValue(0), CounterValue(u64::MAX)));
+    assert_eq!(counter_data.step, 1);
+
+    // Migrating again leaves the counter untouched
+    send(&mut context, &[migrate_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await, counter_data);
+
+    send(&mut context, &[increment_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 4);
+
+    // Its rent can be reclaimed like any other counter's
+    let mut close_ix = counter_ix(program_id, CounterInstruction::CloseCounter, &authority, &counter);
+    close_ix.accounts.push(AccountMeta::new(Pubkey::new_unique(), false));
+    send(&mut context, &[close_ix], &[]).await.unwrap();
+    assert!(context.banks_client.get_account(counter).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_migrate_requires_authority() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let intruder = Keypair::new();
+
+    let migrate_ix = counter_ix(program_id, CounterInstruction::Migrate, &intruder.pubkey(), &counter);
+    let err = send(&mut context, &[migrate_ix], &[&intruder]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::Unauthorized));
+}
+
+#[tokio::test]
+async fn test_zero_copy_view_agrees_with_borsh() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+
+    // Plain increments take the in-place path
+    let increment_ix = instruction_builders::increment(&program_id, &authority, &counter);
+    send(&mut context, &[increment_ix.clone(), increment_ix.clone(), increment_ix], &[])
+        .await
+        .unwrap();
+
+    let account = context.banks_client.get_account(counter).await.unwrap().unwrap();
+    let counter_data = decode_counter(&account.data).unwrap();
+    let zero_copy = *CounterData::load(&account.data).unwrap();
+    let (count, update_count, history) = (zero_copy.count, zero_copy.update_count, zero_copy.history);
+    assert_eq!(count, 3);
+    assert_eq!(count, counter_data.count);
+    assert_eq!(update_count, counter_data.update_count);
+    assert_eq!(history, counter_data.history);
+    assert_eq!(counter_data.history_in_order(), vec![1, 2, 3]);
+}
+
+#[test]
+fn test_read_counter_returns_count_and_authority() {
+    let authority = Pubkey::new_unique();
+    let mut data = vec![0; CounterAccount::LEN];
+    CounterAccount {
+        count: CounterValue(21),
+        ..sample_counter(authority)
+    }
+    .serialize(&mut &mut data[..])
+    .unwrap();
+
+    let (key, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut lamports = 0;
+    let account = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+    assert_eq!(read_counter(&account).unwrap(), (21, authority));
+}
+
+#[tokio::test]
+async fn test_init_and_update_config() {
+    let (program_id, mut context) = start_program().await;
+    let admin = context.payer.pubkey();
+    let (config, _) = ConfigAccount::address(&program_id);
+
+    send(&mut context, &[init_config_ix(program_id, &admin, 50, 10)], &[]).await.unwrap();
+    let account = context.banks_client.get_account(config).await.unwrap().unwrap();
+    assert_eq!(
+        ConfigAccount::try_from_slice(&account.data).unwrap(),
+        ConfigAccount {
+            admin,
+            default_max_value: 50,
+            default_cooldown_secs: 10,
+        }
+    );
+
+    let update_ix = |signer: Pubkey| {
+        let update = CounterInstruction::UpdateConfig {
+            default_max_value: 80,
+            default_cooldown_secs: 0,
+        };
+        counter_ix(program_id, update, &signer, &config)
+    };
+    let intruder = Keypair::new();
+    let err = send(&mut context, &[update_ix(intruder.pubkey())], &[&intruder]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::Unauthorized));
+
+    send(&mut context, &[update_ix(admin)], &[]).await.unwrap();
+    let account = context.banks_client.get_account(config).await.unwrap().unwrap();
+    let config_data = ConfigAccount::try_from_slice(&account.data).unwrap();
+    assert_eq!(config_data.default_max_value, 80);
+    assert_eq!(config_data.default_cooldown_secs, 0);
+}
+
+#[tokio::test]
+async fn test_initialize_inherits_config_defaults() {
+    let (program_id, mut context) = start_program().await;
+    let payer = context.payer.pubkey();
+    let (config, _) = ConfigAccount::address(&program_id);
+    send(&mut context, &[init_config_ix(program_id, &payer, 3, 0)], &[]).await.unwrap();
+
+    let counter_keypair = Keypair::new();
+    let counter = counter_keypair.pubkey();
+    let create_account_ix = system_instruction::create_account(
+        &payer,
+        &counter,
+        Rent::default().minimum_balance(CounterAccount::LEN),
+        CounterAccount::LEN as u64,
+        &program_id,
+    );
+    let mut initialize_ix = counter_ix(program_id, CounterInstruction::Initialize, &payer, &counter);
+    initialize_ix.accounts.push(AccountMeta::new_readonly(config, false));
+    send(&mut context, &[create_account_ix, initialize_ix], &[&counter_keypair]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.max_value, 3);
+
+    // Without the config account the built-in defaults still apply
+    let plain = create_counter(&mut context, program_id).await;
+    assert_eq!(get_counter(&mut context, plain).await.max_value, u64::MAX);
+}
+
+#[tokio::test]
+async fn test_auto_reset_cycles_between_limits() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithConfig {
+            config: CounterConfig {
+                min_value: 1,
+                max_value: 3,
+                auto_reset: true,
+                ..CounterConfig::default()
+            },
+        },
+    )
+    .await;
+    let authority = context.payer.pubkey();
+
+    let increment_ix = instruction_builders::increment(&program_id, &authority, &counter);
+    send(&mut context, &[increment_ix.clone(), increment_ix.clone()], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 3);
+
+    let logs = send_with_logs(&mut context, std::slice::from_ref(&increment_ix), &[]).await;
+    assert_eq!(get_counter(&mut context, counter).await.count, 1);
+    assert_eq!(
+        extract_events(&logs),
+        vec![
+            CounterEvent::Incremented { counter, count: 1 },
+            CounterEvent::Cycled { counter, count: 1 },
+        ]
+    );
+
+    let decrement_ix = instruction_builders::decrement(&program_id, &authority, &counter);
+    send(&mut context, &[decrement_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 3);
+}
+
+#[tokio::test]
+async fn test_authority_set_members_share_control() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let first = context.payer.pubkey();
+    let second = Keypair::new();
+
+    let add_ix = counter_ix(
+        program_id,
+        CounterInstruction::AddAuthority { member: second.pubkey() },
+        &first,
+        &counter,
+    );
+    send(&mut context, &[add_ix], &[]).await.unwrap();
+
+    let increment_ix = instruction_builders::increment(&program_id, &second.pubkey(), &counter);
+    send(&mut context, &[increment_ix], &[&second]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 1);
+
+    let remove_ix = |member: Pubkey| {
+        counter_ix(program_id, CounterInstruction::RemoveAuthority { member }, &second.pubkey(), &counter)
+    };
+    send(&mut context, &[remove_ix(first)], &[&second]).await.unwrap();
+    let counter_data = get_counter(&mut context, counter).await;
+    assert_eq!(counter_data.authority, second.pubkey());
+    assert!(counter_data.authorities.is_empty());
+
+    let increment_ix = instruction_builders::increment(&program_id, &first, &counter);
+    let err = send(&mut context, &[increment_ix], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::Unauthorized));
+
+    let err = send(&mut context, &[remove_ix(second.pubkey())], &[&second]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::LastAuthority));
+}
+
+#[tokio::test]
+async fn test_counter_address_matches_initialize_pda() {
+    let (program_id, mut context) = start_program().await;
+    let authority = context.payer.pubkey();
+
+    let (address, bump) = counter_address(&program_id, &authority, "sample");
+    let (initialize_ix, counter, _) = initialize_pda_ix(program_id, &authority, "sample");
+    assert_eq!(address, counter);
+    send(&mut context, &[initialize_ix], &[]).await.unwrap();
+
+    assert_eq!(get_counter(&mut context, address).await.bump, bump);
+}
+
+#[tokio::test]
+async fn test_decrement_to_zero_clamps() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithValue { initial: 3 },
+    )
+    .await;
+    let authority = context.payer.pubkey();
+
+    let decrement_ix = counter_ix(
+        program_id,
+        CounterInstruction::DecrementToZero { amount: 10 },
+        &authority,
+        &counter,
+    );
+    send(&mut context, &[decrement_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 0);
+}
+
+#[tokio::test]
+async fn test_wrong_discriminator_rejected() {
+    let (program_id, mut context) = start_program().await;
+    let authority = context.payer.pubkey();
+
+    // A program-owned account that decodes as a counter apart from its first bytes
+    let mut data = vec![0; CounterAccount::LEN];
+    sample_counter(authority).serialize(&mut &mut data[..]).unwrap();
+    data[..8].copy_from_slice(b"notcount");
+    let counter = store_counter_data(&mut context, program_id, data);
+
+    for instruction in [CounterInstruction::Increment, CounterInstruction::Reset] {
+        let instruction = counter_ix(program_id, instruction, &authority, &counter);
+        let err = send(&mut context, &[instruction], &[]).await.unwrap_err();
+        assert_eq!(err, counter_error(CounterError::InvalidAccountType));
+    }
+}
+
+#[tokio::test]
+async fn test_initialize_if_needed_is_idempotent() {
+    let (program_id, mut context) = start_program().await;
+    let authority = context.payer.pubkey();
+
+    let counter = create_counter_with(&mut context, program_id, CounterInstruction::InitializeIfNeeded)
+        .await;
+    let increment_ix = instruction_builders::increment(&program_id, &authority, &counter);
+    send(&mut context, &[increment_ix], &[]).await.unwrap();
+
+    // A retried initialize succeeds and keeps the existing state
+    let retry_ix = counter_ix(program_id, CounterInstruction::InitializeIfNeeded, &authority, &counter);
+    send(&mut context, &[retry_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 1);
+}
+
+#[tokio::test]
+async fn test_initialize_if_needed_rejects_garbage() {
+    let (program_id, mut context) = start_program().await;
+    let authority = context.payer.pubkey();
+    let counter = store_counter_data(&mut context, program_id, vec![0xAB; CounterAccount::LEN]);
+
+    let initialize_ix =
+        counter_ix(program_id, CounterInstruction::InitializeIfNeeded, &authority, &counter);
+    let err = send(&mut context, &[initialize_ix], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::InvalidAccountType));
+}
+
+#[tokio::test]
+async fn test_oversized_counter_account_rejected() {
+    let (program_id, mut context) = start_program().await;
+    let authority = context.payer.pubkey();
+
+    let mut data = vec![0; CounterAccount::LEN + 16];
+    sample_counter(authority).serialize(&mut &mut data[..]).unwrap();
+    let counter = store_counter_data(&mut context, program_id, data);
+
+    let increment_ix = instruction_builders::increment(&program_id, &authority, &counter);
+    let err = send(&mut context, &[increment_ix], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::InvalidAccountSize));
+}
+
+#[tokio::test]
+async fn test_multiply_by() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithValue { initial: 5 },
+    )
+    .await;
+    let authority = context.payer.pubkey();
+
+    let multiply_ix = |factor| {
+        counter_ix(program_id, CounterInstruction::MultiplyBy { factor }, &authority, &counter)
+    };
+    send(&mut context, &[multiply_ix(3)], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 15);
+
+    send(&mut context, &[multiply_ix(0)], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 0);
+}
+
+#[tokio::test]
+async fn test_multiply_by_overflow() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithValue { initial: u64::MAX / 2 + 1 },
+    )
+    .await;
+    let authority = context.payer.pubkey();
+
+    let multiply_ix = counter_ix(
+        program_id,
+        CounterInstruction::MultiplyBy { factor: 2 },
+        &authority,
+        &counter,
+    );
+    let err = send(&mut context, &[multiply_ix], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::Overflow));
+    assert_eq!(get_counter(&mut context, counter).await.count, u64::MAX / 2 + 1);
+}
+
+#[tokio::test]
+async fn test_divide_by_rounding() {
+    let (program_id, mut context) = start_program().await;
+    let authority = context.payer.pubkey();
+
+    for (round_up, expected) in [(false, 3), (true, 4)] {
+        let counter = create_counter_with(
+            &mut context,
+            program_id,
+            CounterInstruction::InitializeWithValue { initial: 7 },
+        )
+        .await;
+        let divide_ix = counter_ix(
+            program_id,
+            CounterInstruction::DivideBy { divisor: 2, round_up },
+            &authority,
+            &counter,
+        );
+        send(&mut context, &[divide_ix], &[]).await.unwrap();
+        assert_eq!(get_counter(&mut context, counter).await.count, expected);
+    }
+}
+
+#[tokio::test]
+async fn test_divide_by_zero_rejected() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+
+    let divide_ix = counter_ix(
+        program_id,
+        CounterInstruction::DivideBy { divisor: 0, round_up: false },
+        &authority,
+        &counter,
+    );
+    let err = send(&mut context, &[divide_ix], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::DivideByZero));
+}
+
+#[tokio::test]
+async fn test_assert_count() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithValue { initial: 4 },
+    )
+    .await;
+
+    // Read-only: the counter is the only account and nothing signs for it
+    let assert_ix = |expected| {
+        Instruction::new_with_bytes(
+            program_id,
+            &CounterInstruction::AssertCount { expected }.try_to_vec().unwrap(),
+            vec![AccountMeta::new_readonly(counter, false)],
+        )
+    };
+    send(&mut context, &[assert_ix(4)], &[]).await.unwrap();
+
+    let err = send(&mut context, &[assert_ix(5)], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::AssertionFailed));
+}
+
+#[tokio::test]
+async fn test_verify_authority() {
+    let checker = Pubkey::new_unique();
+    let mut program_test = ProgramTest::default();
+    program_test.add_program("authority_checker", checker, processor!(check_authority));
+    let (_, mut context) = start_program_with(program_test).await;
+    let authority = context.payer.pubkey();
+
+    let check_ix = |stored: Pubkey, signer: Pubkey, is_signer| {
+        Instruction::new_with_bytes(
+            checker,
+            &stored.to_bytes(),
+            vec![AccountMeta::new_readonly(signer, is_signer)],
+        )
+    };
+    send(&mut context, &[check_ix(authority, authority, true)], &[]).await.unwrap();
+
+    let err = send(&mut context, &[check_ix(Pubkey::new_unique(), authority, true)], &[])
+        .await
+        .unwrap_err();
+    assert_eq!(err, counter_error(CounterError::Unauthorized));
+
+    let unsigned = Pubkey::new_unique();
+    let err = send(&mut context, &[check_ix(unsigned, unsigned, false)], &[]).await.unwrap_err();
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(0, InstructionError::MissingRequiredSignature)
+    );
+}
+
+#[tokio::test]
+async fn test_transfer_units() {
+    let (program_id, mut context) = start_program().await;
+    let authority = context.payer.pubkey();
+    let source = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithValue { initial: 8 },
+    )
+    .await;
+    let destination = create_counter(&mut context, program_id).await;
+
+    let transfer_ix = transfer_units_ix(program_id, &authority, &source, &destination, 5);
+    send(&mut context, &[transfer_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, source).await.count, 3);
+    assert_eq!(get_counter(&mut context, destination).await.count, 5);
+}
+
+#[tokio::test]
+async fn test_transfer_units_insufficient_balance() {
+    let (program_id, mut context) = start_program().await;
+    let authority = context.payer.pubkey();
+    let source = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithValue { initial: 3 },
+    )
+    .await;
+    let destination = create_counter(&mut context, program_id).await;
+
+    let transfer_ix = transfer_units_ix(program_id, &authority, &source, &destination, 5);
+    let err = send(&mut context, &[transfer_ix], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::Underflow));
+    assert_eq!(get_counter(&mut context, source).await.count, 3);
+    assert_eq!(get_counter(&mut context, destination).await.count, 0);
+}
+
+#[tokio::test]
+async fn test_label_set_at_init_and_renamed() {
+    let (program_id, mut context) = start_program().await;
+    let authority = context.payer.pubkey();
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithLabel { label: "visits".to_string() },
+    )
+    .await;
+    assert_eq!(get_counter(&mut context, counter).await.label, "visits");
+
+    let rename_ix = counter_ix(
+        program_id,
+        CounterInstruction::SetLabel { label: "page views".to_string() },
+        &authority,
+        &counter,
+    );
+    send(&mut context, &[rename_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.label, "page views");
+
+    // Only the authority may rename the counter
+    let stranger = Keypair::new();
+    let rename_ix = counter_ix(
+        program_id,
+        CounterInstruction::SetLabel { label: "hijacked".to_string() },
+        &stranger.pubkey(),
+        &counter,
+    );
+    let err = send(&mut context, &[rename_ix], &[&stranger]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::Unauthorized));
+    assert_eq!(get_counter(&mut context, counter).await.label, "page views");
+}
+
+#[tokio::test]
+async fn test_label_too_long_rejected() {
+    let (program_id, mut context) = start_program().await;
+    let authority = context.payer.pubkey();
+    let too_long = "x".repeat(MAX_LABEL_LEN + 1);
+
+    let err = try_create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithLabel { label: too_long.clone() },
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err, counter_error_at(1, CounterError::LabelTooLong));
+
+    // A label of exactly the cap fills the reserved space
+    let longest = "x".repeat(MAX_LABEL_LEN);
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithLabel { label: longest.clone() },
+    )
+    .await;
+    let rename_ix = counter_ix(
+        program_id,
+        CounterInstruction::SetLabel { label: too_long },
+        &authority,
+        &counter,
+    );
+    let err = send(&mut context, &[rename_ix], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::LabelTooLong));
+    assert_eq!(get_counter(&mut context, counter).await.label, longest);
+}
+
+#[test]
+fn test_try_read_count() {
+    let mut data = vec![0; CounterAccount::LEN];
+    CounterAccount {
+        count: CounterValue(13),
+        ..sample_counter(Pubkey::new_unique())
+    }
+    .serialize(&mut &mut data[..])
+    .unwrap();
+    assert_eq!(try_read_count(&data), Some(13));
+
+    // Missing and never-initialized accounts read as no counter
+    assert_eq!(try_read_count(&[]), None);
+    assert_eq!(try_read_count(&[0; CounterAccount::LEN]), None);
+
+    assert_eq!(try_read_count(&[0xAB; 64]), None);
+}
+
+#[test]
+fn test_client_fetch_round_trips_initialized_counter() {
+    let program_id = Pubkey::new_unique();
+    let payer = Keypair::new();
+    let counter = Keypair::new();
+    let stored = CounterAccount {
+        count: CounterValue(4),
+        ..sample_counter(payer.pubkey())
+    };
+    let mut data = vec![0; CounterAccount::LEN];
+    stored.serialize(&mut &mut data[..]).unwrap();
+
+    // The mock node accepts every transaction and then serves the counter once
+    let account_info = json!({
+        "context": { "slot": 1 },
+        "value": {
+            "data": [BASE64.encode(&data), "base64"],
+            "executable": false,
+            "lamports": Rent::default().minimum_balance(data.len()),
+            "owner": program_id.to_string(),
+            "rentEpoch": 0,
+            "space": data.len(),
+        },
+    });
+    let mocks = HashMap::from([(RpcRequest::GetAccountInfo, account_info)]);
+    let client =
+        CounterClient::with_rpc_client(RpcClient::new_mock_with_mocks("succeeds", mocks), program_id);
+
+    client.initialize(&payer, &counter).unwrap();
+    client.increment(&payer, &counter.pubkey()).unwrap();
+    assert_eq!(client.fetch(&counter.pubkey()).unwrap(), stored);
+
+    // Without the mocked counter the account does not exist
+    assert!(matches!(client.fetch(&counter.pubkey()), Err(ClientError::Rpc(_))));
+}
+
+#[tokio::test]
+async fn test_authority_changes_emit_events() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let old_authority = context.payer.pubkey();
+    let new_authority = Keypair::new();
+
+    let mut transfer_ix = counter_ix(program_id, CounterInstruction::TransferAuthority, &old_authority, &counter);
+    transfer_ix.accounts.push(AccountMeta::new_readonly(new_authority.pubkey(), false));
+    let logs = send_with_logs(&mut context, &[transfer_ix], &[]).await;
+    assert_eq!(
+        extract_events(&logs),
+        vec![CounterEvent::AuthorityChanged {
+            counter,
+            old: old_authority,
+            new: new_authority.pubkey(),
+        }]
+    );
+
+    // Handing control back through the two-step flow reports the change on accept
+    let propose_ix = counter_ix(
+        program_id,
+        CounterInstruction::ProposeAuthority {
+            new_authority: Some(old_authority),
+        },
+        &new_authority.pubkey(),
+        &counter,
+    );
+    let logs = send_with_logs(&mut context, &[propose_ix], &[&new_authority]).await;
+    assert_eq!(extract_events(&logs), vec![]);
+
+    let accept_ix = counter_ix(program_id, CounterInstruction::AcceptAuthority, &old_authority, &counter);
+    let logs = send_with_logs(&mut context, &[accept_ix], &[]).await;
+    assert_eq!(
+        extract_events(&logs),
+        vec![CounterEvent::AuthorityChanged {
+            counter,
+            old: new_authority.pubkey(),
+            new: old_authority,
+        }]
+    );
+}
+
+#[tokio::test]
+async fn test_labelled_counter_under_allocated_rejected() {
+    let (program_id, mut context) = start_program().await;
+    let authority = context.payer.pubkey();
+
+    // Sized for a counter without room for its label
+    let counter = store_counter_data(&mut context, program_id, vec![0; CounterAccount::LEN - MAX_LABEL_LEN]);
+    let initialize_ix = counter_ix(
+        program_id,
+        CounterInstruction::InitializeWithLabel { label: "visits".to_string() },
+        &authority,
+        &counter,
+    );
+    let err = send(&mut context, &[initialize_ix], &[]).await.unwrap_err();
+    assert_eq!(err, TransactionError::InstructionError(0, InstructionError::AccountDataTooSmall));
+
+    let account = context.banks_client.get_account(counter).await.unwrap().unwrap();
+    assert!(account.data.iter().all(|byte| *byte == 0));
+}
+
+#[tokio::test]
+async fn test_double_increment() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+
+    let double_ix = counter_ix(program_id, CounterInstruction::DoubleIncrement, &authority, &counter);
+    let logs = send_with_logs(&mut context, &[double_ix], &[]).await;
+    assert_eq!(
+        extract_events(&logs),
+        vec![CounterEvent::Incremented { counter, count: 2 }]
+    );
+    assert_eq!(get_counter(&mut context, counter).await.update_count, 1);
+}
+
+#[tokio::test]
+async fn test_increment_by_counts_as_one_update() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+
+    // The same change as two Increments, recorded as a single update
+    let increment_by_ix = counter_ix(
+        program_id,
+        CounterInstruction::IncrementBy { amount: 2 },
+        &authority,
+        &counter,
+    );
+    send(&mut context, &[increment_by_ix], &[]).await.unwrap();
+    let counter_data = get_counter(&mut context, counter).await;
+    assert_eq!(counter_data.count, 2);
+    assert_eq!(counter_data.update_count, 1);
+
+    let increment_ix = instruction_builders::increment(&program_id, &authority, &counter);
+    send(&mut context, &[increment_ix.clone(), increment_ix], &[]).await.unwrap();
+    let counter_data = get_counter(&mut context, counter).await;
+    assert_eq!(counter_data.count, 4);
+    assert_eq!(counter_data.update_count, 3);
+}
+
+#[tokio::test]
+async fn test_get_count_checked_from_another_program() {
+    let reader = Pubkey::new_unique();
+    let mut program_test = ProgramTest::default();
+    program_test.add_program("count_reader", reader, processor!(check_count));
+    let (program_id, mut context) = start_program_with(program_test).await;
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithValue { initial: 3 },
+    )
+    .await;
+
+    let read_ix = |counter_program: Pubkey, expected: u64| {
+        Instruction::new_with_bytes(
+            reader,
+            &(counter_program, expected).try_to_vec().unwrap(),
+            vec![AccountMeta::new_readonly(counter, false)],
+        )
+    };
+    let logs = send_with_logs(&mut context, &[read_ix(program_id, 3)], &[]).await;
+    assert!(logs.iter().any(|line| line.ends_with("Read count: 3")));
+
+    let err = send(&mut context, &[read_ix(program_id, 4)], &[]).await.unwrap_err();
+    assert_eq!(err, TransactionError::InstructionError(0, InstructionError::InvalidArgument));
+
+    // A counter is only trusted when owned by the program the caller expects
+    let err = send(&mut context, &[read_ix(Pubkey::new_unique(), 3)], &[]).await.unwrap_err();
+    assert_eq!(err, TransactionError::InstructionError(0, InstructionError::IncorrectProgramId));
+}
+
+#[tokio::test]
+async fn test_initialize_many() {
+    let (program_id, mut context) = start_program().await;
+    let payer = context.payer.pubkey();
+    let existing = create_counter(&mut context, program_id).await;
+
+    let counters: Vec<Keypair> = (0..3).map(|_| Keypair::new()).collect();
+    let rent_exemption = Rent::default().minimum_balance(CounterAccount::LEN);
+    let mut instructions: Vec<Instruction> = counters
+        .iter()
+        .map(|counter| {
+            system_instruction::create_account(
+                &payer,
+                &counter.pubkey(),
+                rent_exemption,
+                CounterAccount::LEN as u64,
+                &program_id,
+            )
+        })
+        .collect();
+    let initialize_many_ix = |counter_keys: &[Pubkey]| {
+        let mut accounts = vec![AccountMeta::new(payer, true)];
+        accounts.extend(counter_keys.iter().map(|key| AccountMeta::new(*key, false)));
+        Instruction::new_with_bytes(
+            program_id,
+            &CounterInstruction::InitializeMany {
+                count: counter_keys.len() as u8,
+            }
+            .try_to_vec()
+            .unwrap(),
+            accounts,
+        )
+    };
+    let keys: Vec<Pubkey> = counters.iter().map(|counter| counter.pubkey()).collect();
+    let signers: Vec<&Keypair> = counters.iter().collect();
+
+    // One already initialized account aborts the whole batch
+    let mut with_existing = keys.clone();
+    with_existing.push(existing);
+    let mut failing = instructions.clone();
+    failing.push(initialize_many_ix(&with_existing));
+    let err = send(&mut context, &failing, &signers).await.unwrap_err();
+    assert_eq!(err, counter_error_at(3, CounterError::AlreadyInitialized));
+    for key in &keys {
+        assert!(context.banks_client.get_account(*key).await.unwrap().is_none());
+    }
+
+    instructions.push(initialize_many_ix(&keys));
+    send(&mut context, &instructions, &signers).await.unwrap();
+    for key in keys {
+        let counter_data = get_counter(&mut context, key).await;
+        assert_eq!(counter_data.count, 0);
+        assert_eq!(counter_data.authority, payer);
+    }
+}
+
+#[tokio::test]
+async fn test_foreign_owned_counter_rejected() {
+    let (program_id, mut context) = start_program().await;
+    let authority = context.payer.pubkey();
+
+    // A valid counter layout held by an account another program owns
+    let mut data = vec![0; CounterAccount::LEN];
+    CounterAccount {
+        count: CounterValue(5),
+        ..sample_counter(authority)
+    }
+    .serialize(&mut &mut data[..])
+    .unwrap();
+    let counter = store_counter_data(&mut context, Pubkey::new_unique(), data);
+
+    let increment_ix = instruction_builders::increment(&program_id, &authority, &counter);
+    let decrement_ix = instruction_builders::decrement(&program_id, &authority, &counter);
+    for instruction in [increment_ix, decrement_ix] {
+        let err = send(&mut context, &[instruction], &[]).await.unwrap_err();
+        assert_eq!(err, TransactionError::InstructionError(0, InstructionError::IncorrectProgramId));
+    }
+}
+
+#[tokio::test]
+async fn test_oversized_batch_rejected() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+
+    let batch_ix = |len| {
+        counter_ix(
+            program_id,
+            CounterInstruction::Batch { ops: vec![1; len] },
+            &authority,
+            &counter,
+        )
+    };
+    let err = send(&mut context, &[batch_ix(MAX_BATCH_OPS + 1)], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::TooLarge));
+    let counter_data = get_counter(&mut context, counter).await;
+    assert_eq!(counter_data.count, 0);
+    assert_eq!(counter_data.update_count, 0);
+
+    send(&mut context, &[batch_ix(MAX_BATCH_OPS)], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, MAX_BATCH_OPS as u64);
+}
+
+#[tokio::test]
+async fn test_counter_generations_have_distinct_nonces() {
+    let (program_id, mut context) = start_program().await;
+    let authority = context.payer.pubkey();
+
+    let initialize_generation_ix = |nonce: u64| {
+        let (counter, _) = counter_generation_address(&program_id, &authority, "grid", nonce);
+        let instruction = Instruction::new_with_bytes(
+            program_id,
+            &CounterInstruction::InitializePdaWithNonce {
+                seed: "grid".to_string(),
+                nonce,
+            }
+            .try_to_vec()
+            .unwrap(),
+            vec![
+                AccountMeta::new(authority, true),
+                AccountMeta::new(counter, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        (instruction, counter)
+    };
+
+    let (initialize_ix, first) = initialize_generation_ix(1);
+    let logs = send_with_logs(&mut context, &[initialize_ix], &[]).await;
+    assert_eq!(
+        extract_events(&logs),
+        vec![CounterEvent::Initialized { counter: first, count: 0, nonce: 1 }]
+    );
+
+    let mut close_ix = counter_ix(program_id, CounterInstruction::CloseCounter, &authority, &first);
+    close_ix.accounts.push(AccountMeta::new(authority, false));
+    send(&mut context, &[close_ix], &[]).await.unwrap();
+
+    let (initialize_ix, second) = initialize_generation_ix(2);
+    let logs = send_with_logs(&mut context, &[initialize_ix], &[]).await;
+    assert_eq!(
+        extract_events(&logs),
+        vec![CounterEvent::Initialized { counter: second, count: 0, nonce: 2 }]
+    );
+    assert_ne!(first, second);
+    assert_eq!(get_counter(&mut context, second).await.nonce, 2);
+
+    // The nonce is part of the address the program checks on later changes
+    let increment_ix = instruction_builders::increment(&program_id, &authority, &second);
+    send(&mut context, &[increment_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, second).await.count, 1);
+
+    // Generation 0 is the plain `InitializePda` counter
+    assert_eq!(
+        counter_generation_address(&program_id, &authority, "grid", 0),
+        counter_address(&program_id, &authority, "grid")
+    );
+}
+
+#[tokio::test]
+async fn test_read_only_counter_rejected() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+
+    let mut increment_ix = instruction_builders::increment(&program_id, &authority, &counter);
+    increment_ix.accounts[1].is_writable = false;
+    let err = send(&mut context, &[increment_ix], &[]).await.unwrap_err();
+    assert_eq!(err, TransactionError::InstructionError(0, InstructionError::InvalidArgument));
+    assert_eq!(get_counter(&mut context, counter).await.count, 0);
+}
+
+#[tokio::test]
+async fn test_epoch_window_resets_on_new_epoch() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithConfig {
+            config: CounterConfig {
+                min_value: 1,
+                epoch_window: true,
+                ..CounterConfig::default()
+            },
+        },
+    )
+    .await;
+    let authority = context.payer.pubkey();
+
+    let increment_ix = instruction_builders::increment(&program_id, &authority, &counter);
+    send(&mut context, &[increment_ix.clone(), increment_ix.clone()], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 3);
+
+    let clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    let next_epoch_slot = context
+        .genesis_config()
+        .epoch_schedule
+        .get_first_slot_in_epoch(clock.epoch + 1);
+    context.warp_to_slot(next_epoch_slot).unwrap();
+
+    // The first change of the new epoch starts from `min_value`
+    send(&mut context, &[increment_ix], &[]).await.unwrap();
+    let counter_data = get_counter(&mut context, counter).await;
+    assert_eq!(counter_data.count, 2);
+    assert_eq!(counter_data.last_epoch, clock.epoch + 1);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_counter_json_round_trip() {
+    let authority = Pubkey::new_unique();
+    let counter = CounterAccount {
+        count: CounterValue(12),
+        delegate: Some(Pubkey::new_unique()),
+        authorities: vec![Pubkey::new_unique()],
+        label: "visits".to_string(),
+        ..sample_counter(authority)
+    };
+    let json = serde_json::to_value(&counter).unwrap();
+    assert_eq!(json["authority"], authority.to_string());
+    assert_eq!(serde_json::from_value::<CounterAccount>(json).unwrap(), counter);
+
+    let member = Pubkey::new_unique();
+    let cases = [
+        (CounterInstruction::IncrementBy { amount: 5 }, json!({ "IncrementBy": { "amount": 5 } })),
+        (
+            CounterInstruction::AddAuthority { member },
+            json!({ "AddAuthority": { "member": member.to_string() } }),
+        ),
+        (
+            CounterInstruction::ProposeAuthority { new_authority: None },
+            json!({ "ProposeAuthority": { "new_authority": null } }),
+        ),
+        (
+            CounterInstruction::InitializeManyWithAuthorities { authorities: vec![authority, member] },
+            json!({
+                "InitializeManyWithAuthorities": { "authorities": [authority.to_string(), member.to_string()] }
+            }),
+        ),
+    ];
+    for (instruction, expected) in cases {
+        let json = serde_json::to_value(&instruction).unwrap();
+        assert_eq!(json, expected);
+        let decoded: CounterInstruction = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.try_to_vec().unwrap(), instruction.try_to_vec().unwrap());
+    }
+
+    assert!(serde_json::from_value::<CounterInstruction>(json!({ "AddAuthority": { "member": "not a key" } })).is_err());
+}
+
+#[test]
+fn test_counter_value_matches_u64_layout() {
+    let value = CounterValue(0x0102_0304_0506_0708);
+    assert_eq!(value.try_to_vec().unwrap(), 0x0102_0304_0506_0708u64.try_to_vec().unwrap());
+    assert_eq!(CounterValue::try_from_slice(&u64::MAX.to_le_bytes()).unwrap(), u64::MAX);
+
+    // The count still sits right after the discriminator and version byte
+    let mut history = [CounterValue(0); HISTORY_LEN];
+    history[0] = value;
+    let counter = CounterAccount {
+        count: value,
+        min_value: CounterValue(1),
+        max_value: CounterValue(u64::MAX - 1),
+        history,
+        ..sample_counter(Pubkey::new_unique())
+    };
+    let data = counter.try_to_vec().unwrap();
+    assert_eq!(data[9..17], 0x0102_0304_0506_0708u64.to_le_bytes());
+    assert_eq!(decode_counter(&data).unwrap().count, value);
+
+    // The zero-copy view reads the same bytes, limits and history included
+    let view = CounterData::load(&data).unwrap();
+    assert_eq!({ view.count }, value);
+    assert_eq!(({ view.min_value }, { view.max_value }), (counter.min_value, counter.max_value));
+    assert_eq!({ view.history }, counter.history);
+
+    assert_eq!(value.checked_add(1), Some(CounterValue(0x0102_0304_0506_0709)));
+    assert_eq!(CounterValue(u64::MAX).checked_add(1), None);
+    assert_eq!(CounterValue(0).checked_sub(1), None);
+}
+
+#[tokio::test]
+async fn test_reset_to_initial_restores_initial_value() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithValue { initial: 100 },
+    )
+    .await;
+    let authority = context.payer.pubkey();
+
+    let increment_ix = instruction_builders::increment(&program_id, &authority, &counter);
+    send(&mut context, &[increment_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 101);
+
+    let reset_ix = counter_ix(program_id, CounterInstruction::ResetToInitial, &authority, &counter);
+    send(&mut context, &[reset_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 100);
+
+    // A plain reset still goes to zero
+    let reset_ix = counter_ix(program_id, CounterInstruction::Reset, &authority, &counter);
+    send(&mut context, &[reset_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 0);
+}
+
+#[cfg(feature = "debug-compute")]
+#[tokio::test]
+async fn test_debug_compute_logs_around_instruction() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+
+    let increment_ix = instruction_builders::increment(&program_id, &authority, &counter);
+    let logs = send_with_logs(&mut context, &[increment_ix], &[]).await;
+    assert_eq!(get_counter(&mut context, counter).await.count, 1);
+
+    // Natively the syscall stub logs a placeholder instead of the units left
+    let compute_logs = logs
+        .iter()
+        .filter(|log| log.contains("sol_log_compute_units") || log.contains("units remaining"))
+        .count();
+    assert_eq!(compute_logs, 2);
+}
+
+#[tokio::test]
+async fn test_read_many_returns_counts() {
+    let (program_id, mut context) = start_program().await;
+    let mut counters = Vec::new();
+    for initial in [5, 0, 42] {
+        counters.push(
+            create_counter_with(&mut context, program_id, CounterInstruction::InitializeWithValue { initial })
+                .await,
+        );
+    }
+
+    let read_ix = Instruction::new_with_bytes(
+        program_id,
+        &CounterInstruction::ReadMany.try_to_vec().unwrap(),
+        counters.iter().map(|counter| AccountMeta::new_readonly(*counter, false)).collect(),
+    );
+    let data = return_data(&mut context, &[read_ix]).await;
+    let counts: Vec<u64> = data
+        .chunks_exact(8)
+        .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+        .collect();
+    assert_eq!(counts, vec![5, 0, 42]);
+}
+
+#[tokio::test]
+async fn test_indexed_counters_share_authority() {
+    let (program_id, mut context) = start_program().await;
+    let authority = context.payer.pubkey();
+
+    let mut counters = Vec::new();
+    for index in [0, 1] {
+        let (counter, _) = counter_address_for(&program_id, &authority, index);
+        let initialize_ix = Instruction::new_with_bytes(
+            program_id,
+            &CounterInstruction::InitializeIndexed { index }.try_to_vec().unwrap(),
+            vec![
+                AccountMeta::new(authority, true),
+                AccountMeta::new(counter, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+        );
+        send(&mut context, &[initialize_ix], &[]).await.unwrap();
+
+        let counter_data = get_counter(&mut context, counter).await;
+        assert_eq!(counter_data.index, index);
+        assert_eq!(counter_data.authority, authority);
+        counters.push(counter);
+    }
+    assert_ne!(counters[0], counters[1]);
+
+    // Both are PDA counters the authority can change
+    let increment_ix = instruction_builders::increment(&program_id, &authority, &counters[1]);
+    send(&mut context, &[increment_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counters[1]).await.count, 1);
+    assert_eq!(get_counter(&mut context, counters[0]).await.count, 0);
+}
+
+#[tokio::test]
+async fn test_initialize_rejects_unsignable_authority() {
+    let (program_id, mut context) = start_program().await;
+
+    for authority in [Pubkey::default(), system_program::id()] {
+        let err = try_create_counter_with(
+            &mut context,
+            program_id,
+            CounterInstruction::InitializeWithAuthority { authority },
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err, counter_error_at(1, CounterError::InvalidAuthority));
+    }
+}
+
+#[tokio::test]
+async fn test_increment_and_return_returns_new_count() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithValue { initial: 41 },
+    )
+    .await;
+    let authority = context.payer.pubkey();
+
+    let increment_ix = counter_ix(program_id, CounterInstruction::IncrementAndReturn, &authority, &counter);
+    let data = return_data(&mut context, &[increment_ix]).await;
+    assert_eq!(u64::from_le_bytes(data.try_into().unwrap()), 42);
+    assert_eq!(get_counter(&mut context, counter).await.count, 42);
+}
+
+#[tokio::test]
+async fn test_decay_applies_before_change() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithConfig {
+            config: CounterConfig {
+                min_value: 10,
+                initial_value: Some(100),
+                decay_per_sec: 2,
+                ..CounterConfig::default()
+            },
+        },
+    )
+    .await;
+    let authority = context.payer.pubkey();
+    let last_updated = get_counter(&mut context, counter).await.last_updated;
+
+    // 30 seconds leak 60 before the increment adds 1
+    advance_clock(&mut context, 30).await;
+    let clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    let elapsed = (clock.unix_timestamp - last_updated) as u64;
+    let increment_ix = instruction_builders::increment(&program_id, &authority, &counter);
+    send(&mut context, std::slice::from_ref(&increment_ix), &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 100 - 2 * elapsed + 1);
+
+    // The decay stops at the floor
+    advance_clock(&mut context, 1_000).await;
+    send(&mut context, &[increment_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 11);
+}
+
+#[tokio::test]
+async fn test_withdraw_excess_lamports_keeps_counter() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+    let destination = Pubkey::new_unique();
+
+    // Over-fund the counter
+    let surplus = 1_000_000;
+    let fund_ix = system_instruction::transfer(&authority, &counter, surplus);
+    send(&mut context, &[fund_ix], &[]).await.unwrap();
+
+    let withdraw_ix = Instruction::new_with_bytes(
+        program_id,
+        &CounterInstruction::WithdrawExcessLamports.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new_readonly(authority, true),
+            AccountMeta::new(counter, false),
+            AccountMeta::new(destination, false),
+        ],
+    );
+    send(&mut context, &[withdraw_ix], &[]).await.unwrap();
+
+    let rent_exemption = Rent::default().minimum_balance(CounterAccount::LEN);
+    assert_eq!(context.banks_client.get_balance(destination).await.unwrap(), surplus);
+    assert_eq!(context.banks_client.get_balance(counter).await.unwrap(), rent_exemption);
+
+    // The counter is still open and usable
+    let increment_ix = instruction_builders::increment(&program_id, &authority, &counter);
+    send(&mut context, &[increment_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 1);
+}
+
+#[tokio::test]
+async fn test_strict_signers_rejects_extra_signer() {
+    let (program_id, mut context) = start_program().await;
+    let authority = context.payer.pubkey();
+    let bystander = Keypair::new();
+
+    let increment_with_bystander = |counter: &Pubkey| {
+        let mut increment_ix = instruction_builders::increment(&program_id, &authority, counter);
+        increment_ix.accounts.push(AccountMeta::new_readonly(bystander.pubkey(), true));
+        increment_ix
+    };
+
+    // Extra signers are ignored by default
+    let lenient = create_counter(&mut context, program_id).await;
+    send(&mut context, &[increment_with_bystander(&lenient)], &[&bystander]).await.unwrap();
+    assert_eq!(get_counter(&mut context, lenient).await.count, 1);
+
+    let strict = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithConfig {
+            config: CounterConfig {
+                strict_signers: true,
+                ..CounterConfig::default()
+            },
+        },
+    )
+    .await;
+    let err = send(&mut context, &[increment_with_bystander(&strict)], &[&bystander]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::UnexpectedSigner));
+
+    let increment_ix = instruction_builders::increment(&program_id, &authority, &strict);
+    send(&mut context, &[increment_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, strict).await.count, 1);
+}
+
+#[tokio::test]
+async fn test_snapshot_copies_count_to_new_authority() {
+    let (program_id, mut context) = start_program().await;
+    let payer = context.payer.pubkey();
+    let source = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithValue { initial: 7 },
+    )
+    .await;
+    let new_authority = Keypair::new();
+    let snapshot = Keypair::new();
+
+    let create_account_ix = system_instruction::create_account(
+        &payer,
+        &snapshot.pubkey(),
+        Rent::default().minimum_balance(CounterAccount::LEN),
+        CounterAccount::LEN as u64,
+        &program_id,
+    );
+    let snapshot_ix = Instruction::new_with_bytes(
+        program_id,
+        &CounterInstruction::SnapshotTo {
+            authority: new_authority.pubkey(),
+        }
+        .try_to_vec()
+        .unwrap(),
+        vec![
+            AccountMeta::new_readonly(payer, true),
+            AccountMeta::new_readonly(source, false),
+            AccountMeta::new(snapshot.pubkey(), false),
+        ],
+    );
+    send(&mut context, &[create_account_ix, snapshot_ix], &[&snapshot]).await.unwrap();
+
+    let snapshot_data = get_counter(&mut context, snapshot.pubkey()).await;
+    assert_eq!(snapshot_data.count, 7);
+    assert_eq!(snapshot_data.authority, new_authority.pubkey());
+
+    // The copy moves independently of the source
+    let increment_ix = instruction_builders::increment(&program_id, &new_authority.pubkey(), &snapshot.pubkey());
+    send(&mut context, &[increment_ix], &[&new_authority]).await.unwrap();
+    assert_eq!(get_counter(&mut context, snapshot.pubkey()).await.count, 8);
+    assert_eq!(get_counter(&mut context, source).await.count, 7);
+}
+
+#[tokio::test]
+async fn test_increment_if_reference_meets_threshold() {
+    let (program_id, mut context) = start_program().await;
+    let authority = context.payer.pubkey();
+    let target = create_counter(&mut context, program_id).await;
+    let reference = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithValue { initial: 5 },
+    )
+    .await;
+
+    let increment_if_ix = |threshold: u64| {
+        let mut instruction =
+            counter_ix(program_id, CounterInstruction::IncrementIf { threshold }, &authority, &target);
+        instruction.accounts.insert(0, AccountMeta::new_readonly(reference, false));
+        instruction
+    };
+
+    send(&mut context, &[increment_if_ix(5)], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, target).await.count, 1);
+
+    let err = send(&mut context, &[increment_if_ix(6)], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::ConditionNotMet));
+    assert_eq!(get_counter(&mut context, target).await.count, 1);
+}
+
+#[tokio::test]
+async fn test_empty_instruction_data_rejected() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+
+    let mut empty_ix = instruction_builders::increment(&program_id, &authority, &counter);
+    empty_ix.data.clear();
+    let transaction = sign(&mut context, &[empty_ix], &[]).await;
+    let outcome = context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    assert_eq!(
+        outcome.result.unwrap_err(),
+        TransactionError::InstructionError(0, InstructionError::InvalidInstructionData)
+    );
+    let logs = outcome.metadata.unwrap().log_messages;
+    assert!(logs.iter().any(|log| log.contains("Error: Empty instruction data")));
+    assert_eq!(get_counter(&mut context, counter).await.count, 0);
+}
+
+#[tokio::test]
+async fn test_debug_dump_logs_state_without_changes() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithLabel {
+            label: "visits".to_string(),
+        },
+    )
+    .await;
+    let authority = context.payer.pubkey();
+    let before = context.banks_client.get_account(counter).await.unwrap().unwrap();
+
+    // Anyone can dump the state, the authority does not sign
+    let dump_ix = Instruction::new_with_bytes(
+        program_id,
+        &CounterInstruction::DebugDump.try_to_vec().unwrap(),
+        vec![AccountMeta::new_readonly(counter, false)],
+    );
+    let logs = send_with_logs(&mut context, &[dump_ix], &[]).await;
+    let dump = logs.iter().find(|log| log.contains(&format!("Counter {}:", counter))).unwrap();
+    assert!(dump.contains(&authority.to_string()));
+    assert!(dump.contains("label: \"visits\""));
+
+    let after = context.banks_client.get_account(counter).await.unwrap().unwrap();
+    assert_eq!(after, before);
+}
+
+#[tokio::test]
+async fn test_fixed_point_counter_renders_decimal() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithConfig {
+            config: CounterConfig {
+                scale: 2,
+                ..CounterConfig::default()
+            },
+        },
+    )
+    .await;
+    let authority = context.payer.pubkey();
+
+    let increment_ix = counter_ix(
+        program_id,
+        CounterInstruction::IncrementFixed { raw: 150 },
+        &authority,
+        &counter,
+    );
+    send(&mut context, &[increment_ix], &[]).await.unwrap();
+
+    let counter_data = get_counter(&mut context, counter).await;
+    assert_eq!(counter_data.count, 150);
+    assert_eq!(to_decimal(counter_data.count.0, counter_data.scale), "1.50");
+
+    assert_eq!(to_decimal(5, 2), "0.05");
+    assert_eq!(to_decimal(1234, 0), "1234");
+}
+
+#[tokio::test]
+async fn test_reentrant_change_rejected() {
+    let (program_id, mut context) = start_program().await;
+    let authority = context.payer.pubkey();
+
+    // The state a milestone callback would find: the increment that crossed the
+    // milestone is still running. The runtime already refuses indirect re-entry
+    // into the program; the stored lock also covers any other way back in.
+    let mut data = vec![0; CounterAccount::LEN];
+    CounterAccount {
+        count: CounterValue(3),
+        processing: true,
+        ..sample_counter(authority)
+    }
+    .serialize(&mut &mut data[..])
+    .unwrap();
+    let counter = store_counter_data(&mut context, program_id, data);
+
+    for instruction in [
+        CounterInstruction::Increment,
+        CounterInstruction::Decrement,
+        CounterInstruction::Reset,
+        CounterInstruction::SetValue { value: 9 },
+        CounterInstruction::Undo,
+        CounterInstruction::Batch { ops: vec![1] },
+    ] {
+        let instruction = counter_ix(program_id, instruction, &authority, &counter);
+        let err = send(&mut context, &[instruction], &[]).await.unwrap_err();
+        assert_eq!(err, counter_error(CounterError::Reentrancy));
+    }
+    assert_eq!(get_counter(&mut context, counter).await.count, 3);
+
+    // Each change releases the lock it took before storing the counter
+    let counter = create_counter(&mut context, program_id).await;
+    let ix = |instruction| counter_ix(program_id, instruction, &authority, &counter);
+    let changes = [
+        ix(CounterInstruction::IncrementBy { amount: 4 }),
+        ix(CounterInstruction::Undo),
+        ix(CounterInstruction::SetValue { value: 9 }),
+        ix(CounterInstruction::Reset),
+    ];
+    send(&mut context, &changes, &[]).await.unwrap();
+    assert!(!get_counter(&mut context, counter).await.processing);
+}
+
+#[tokio::test]
+async fn test_initialize_many_with_authorities() {
+    let (program_id, mut context) = start_program().await;
+    let payer = context.payer.pubkey();
+
+    let counters = [Keypair::new(), Keypair::new()];
+    let authorities = vec![Pubkey::new_unique(), Pubkey::new_unique()];
+    let rent_exemption = Rent::default().minimum_balance(CounterAccount::LEN);
+    let mut instructions: Vec<Instruction> = counters
+        .iter()
+        .map(|counter| {
+            system_instruction::create_account(
+                &payer,
+                &counter.pubkey(),
+                rent_exemption,
+                CounterAccount::LEN as u64,
+                &program_id,
+            )
+        })
+        .collect();
+    let initialize_ix = |authorities: Vec<Pubkey>| {
+        let mut accounts = vec![AccountMeta::new(payer, true)];
+        accounts.extend(counters.iter().map(|counter| AccountMeta::new(counter.pubkey(), false)));
+        Instruction::new_with_bytes(
+            program_id,
+            &CounterInstruction::InitializeManyWithAuthorities { authorities }
+                .try_to_vec()
+                .unwrap(),
+            accounts,
+        )
+    };
+    let signers: Vec<&Keypair> = counters.iter().collect();
+
+    // One authority short of the accounts passed
+    let mut mismatched = instructions.clone();
+    mismatched.push(initialize_ix(authorities[..1].to_vec()));
+    let err = send(&mut context, &mismatched, &signers).await.unwrap_err();
+    assert_eq!(err, TransactionError::InstructionError(2, InstructionError::InvalidArgument));
+
+    instructions.push(initialize_ix(authorities.clone()));
+    send(&mut context, &instructions, &signers).await.unwrap();
+    for (counter, authority) in counters.iter().zip(&authorities) {
+        let counter_data = get_counter(&mut context, counter.pubkey()).await;
+        assert_eq!(counter_data.authority, *authority);
+        assert_eq!(counter_data.count, 0);
+    }
+}
+
+#[tokio::test]
+async fn test_growth_tops_up_exact_rent_shortfall() {
+    let (program_id, mut context) = start_program().await;
+    let multi = create_multi_counter(&mut context, program_id, 1).await;
+    let authority = context.payer.pubkey();
+
+    let add_ix = |name: &str| {
+        let mut instruction = counter_ix(
+            program_id,
+            CounterInstruction::AddNamedCounter { name: name.to_string() },
+            &authority,
+            &multi,
+        );
+        instruction.accounts.push(AccountMeta::new(authority, true));
+        instruction.accounts.push(AccountMeta::new_readonly(system_program::id(), false));
+        instruction
+    };
+    // The second name no longer fits the space allocated for one of the longest
+    let longest = "x".repeat(MultiCounterAccount::MAX_NAME_LEN);
+    send(&mut context, &[add_ix(&longest), add_ix("second")], &[]).await.unwrap();
+
+    // Grown past its initial size and funded to exactly the new minimum
+    let account = context.banks_client.get_account(multi).await.unwrap().unwrap();
+    assert!(account.data.len() > MultiCounterAccount::space(1));
+    assert_eq!(account.lamports, Rent::default().minimum_balance(account.data.len()));
+}
+
+#[tokio::test]
+async fn test_repair_history_restores_valid_state() {
+    let (program_id, mut context) = start_program().await;
+    let authority = context.payer.pubkey();
+
+    let mut history = [CounterValue(0); HISTORY_LEN];
+    history[..3].copy_from_slice(&[CounterValue(1), CounterValue(2), CounterValue(500)]);
+    let mut data = vec![0; CounterAccount::LEN];
+    CounterAccount {
+        count: CounterValue(2),
+        update_count: 3,
+        max_value: CounterValue(100),
+        history,
+        history_pos: 200,
+        ..sample_counter(authority)
+    }
+    .serialize(&mut &mut data[..])
+    .unwrap();
+    let counter = store_counter_data(&mut context, program_id, data);
+
+    let repair_ix = counter_ix(program_id, CounterInstruction::RepairHistory, &authority, &counter);
+    let logs = send_with_logs(&mut context, &[repair_ix], &[]).await;
+    assert!(logs.iter().any(|log| log.contains("History position 200 out of range, reset to 3")));
+
+    let counter_data = get_counter(&mut context, counter).await;
+    assert_eq!(counter_data.history_pos, 3);
+    assert_eq!(counter_data.history[..3], [1, 2, 0]);
+    assert_eq!(counter_data.count, 2);
+
+    // The next change lands after the repaired entries
+    let increment_ix = instruction_builders::increment(&program_id, &authority, &counter);
+    send(&mut context, &[increment_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.history_in_order()[..4], [1, 2, 0, 3]);
+}
+
+#[tokio::test]
+async fn test_feature_flags_select_checks() {
+    let (program_id, mut context) = start_program().await;
+    let authority = context.payer.pubkey();
+    let plain = create_counter(&mut context, program_id).await;
+    let featured = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithConfig {
+            config: CounterConfig {
+                rate_limited: true,
+                decay_per_sec: 1,
+                strict_signers: true,
+                ..CounterConfig::default()
+            },
+        },
+    )
+    .await;
+    assert_eq!(get_counter(&mut context, plain).await.flags, 0);
+    assert_eq!(
+        get_counter(&mut context, featured).await.flags,
+        CounterAccount::FLAG_RATE_LIMIT | CounterAccount::FLAG_DECAY | CounterAccount::FLAG_STRICT_SIGNERS
+    );
+
+    let increment_ix = |counter| instruction_builders::increment(&program_id, &authority, &counter);
+    let in_place = |logs: &[String]| logs.iter().filter(|log| log.contains("incremented in place")).count();
+
+    // The plain counter takes the fast path; the featured one still gets its checks
+    let logs = send_with_logs(&mut context, &[increment_ix(plain), increment_ix(plain)], &[]).await;
+    assert_eq!(in_place(&logs), 2);
+    assert_eq!(get_counter(&mut context, plain).await.count, 2);
+    let logs = send_with_logs(&mut context, &[increment_ix(featured)], &[]).await;
+    assert_eq!(in_place(&logs), 0);
+    let err = send(&mut context, &[increment_ix(featured)], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::RateLimited));
+}
+
+#[tokio::test]
+async fn test_increment_uninitialized_account_rejected() {
+    let (program_id, mut context) = start_program().await;
+    let authority = context.payer.pubkey();
+
+    // Allocated for a counter but never initialized, and allocated with no space at all
+    for data in [vec![0; CounterAccount::LEN], Vec::new()] {
+        let counter = store_counter_data(&mut context, program_id, data);
+        let increment_ix = instruction_builders::increment(&program_id, &authority, &counter);
+        let err = send(&mut context, &[increment_ix], &[]).await.unwrap_err();
+        assert_eq!(err, counter_error(CounterError::NotInitialized));
+    }
+}
+
+#[tokio::test]
+async fn test_expired_counter_is_read_only() {
+    let (program_id, mut context) = start_program().await;
+    let clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithConfig {
+            config: CounterConfig {
+                expires_at: clock.unix_timestamp + 60,
+                ..CounterConfig::default()
+            },
+        },
+    )
+    .await;
+    let authority = context.payer.pubkey();
+    let increment_ix = || instruction_builders::increment(&program_id, &authority, &counter);
+
+    send(&mut context, &[increment_ix()], &[]).await.unwrap();
+
+    advance_clock(&mut context, 60).await;
+    let err = send(&mut context, &[increment_ix()], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::Expired));
+    let decrement_ix = instruction_builders::decrement(&program_id, &authority, &counter);
+    let err = send(&mut context, &[decrement_ix], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::Expired));
+
+    // Reads keep working after the expiry
+    assert_eq!(get_counter(&mut context, counter).await.count, 1);
+    let assert_ix = Instruction::new_with_bytes(
+        program_id,
+        &CounterInstruction::AssertCount { expected: 1 }.try_to_vec().unwrap(),
+        vec![AccountMeta::new_readonly(counter, false)],
+    );
+    send(&mut context, &[assert_ix], &[]).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_increment_percent() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithValue { initial: 200 },
+    )
+    .await;
+    let authority = context.payer.pubkey();
+    let percent_ix = |basis_points| {
+        counter_ix(
+            program_id,
+            CounterInstruction::IncrementPercent { basis_points },
+            &authority,
+            &counter,
+        )
+    };
+
+    send(&mut context, &[percent_ix(5_000)], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 300);
+
+    // 0.01% of 300 rounds down to nothing
+    send(&mut context, &[percent_ix(1)], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 300);
+
+    let err = send(&mut context, &[percent_ix(MAX_BASIS_POINTS + 1)], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::InvalidPercentage));
+}
+
+#[tokio::test]
+async fn test_increment_percent_overflow() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithValue { initial: u64::MAX - 10 },
+    )
+    .await;
+    let authority = context.payer.pubkey();
+
+    let percent_ix = counter_ix(
+        program_id,
+        CounterInstruction::IncrementPercent { basis_points: 100 },
+        &authority,
+        &counter,
+    );
+    let err = send(&mut context, &[percent_ix], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::Overflow));
+    assert_eq!(get_counter(&mut context, counter).await.count, u64::MAX - 10);
+}
+
+#[tokio::test]
+async fn test_simulate_reports_outcome_without_changes() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithValue { initial: u64::MAX - 1 },
+    )
+    .await;
+    let authority = context.payer.pubkey();
+    let simulate_ix = |inner| {
+        counter_ix(
+            program_id,
+            CounterInstruction::Simulate { inner: SimulatedInstruction::from(inner) },
+            &authority,
+            &counter,
+        )
+    };
+    let before = context.banks_client.get_account(counter).await.unwrap().unwrap();
+
+    let data = return_data(&mut context, &[simulate_ix(CounterInstruction::Increment)]).await;
+    assert_eq!(
+        SimulationOutcome::try_from_slice(&data).unwrap(),
+        SimulationOutcome::Succeeded { count: u64::MAX }
+    );
+
+    let data = return_data(&mut context, &[simulate_ix(CounterInstruction::IncrementBy { amount: 2 })]).await;
+    let outcome = SimulationOutcome::try_from_slice(&data).unwrap();
+    assert_eq!(
+        outcome,
+        SimulationOutcome::Failed {
+            error: ProgramError::from(CounterError::Overflow).into()
+        }
+    );
+
+    let after = context.banks_client.get_account(counter).await.unwrap().unwrap();
+    assert_eq!(after.data, before.data);
+
+    // Only count changes to a single counter can be simulated
+    let err = send(&mut context, &[simulate_ix(CounterInstruction::CloseCounter)], &[])
+        .await
+        .unwrap_err();
+    assert_eq!(err, TransactionError::InstructionError(0, InstructionError::InvalidInstructionData));
+}
+
+#[tokio::test]
+async fn test_authority_set_capped() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+    let add_ix = |member| counter_ix(program_id, CounterInstruction::AddAuthority { member }, &authority, &counter);
+
+    let members: Vec<Pubkey> = (0..MAX_AUTHORITIES).map(|_| Pubkey::new_unique()).collect();
+    for member in &members {
+        send(&mut context, &[add_ix(*member)], &[]).await.unwrap();
+    }
+    assert_eq!(get_counter(&mut context, counter).await.authorities, members);
+
+    let err = send(&mut context, &[add_ix(Pubkey::new_unique())], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::TooManyAuthorities));
+
+    // The full set fits the space reserved at creation
+    let account = context.banks_client.get_account(counter).await.unwrap().unwrap();
+    assert_eq!(account.data.len(), CounterAccount::LEN);
+}
+
+#[tokio::test]
+async fn test_increment_emits_single_event() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+
+    let increment_ix = instruction_builders::increment(&program_id, &authority, &counter);
+    let logs = send_with_logs(&mut context, &[increment_ix], &[]).await;
+    assert_eq!(extract_events(&logs), vec![CounterEvent::Incremented { counter, count: 1 }]);
+}
+
+#[tokio::test]
+async fn test_update_limits() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithLimits { min_value: 0, max_value: 10 },
+    )
+    .await;
+    let authority = context.payer.pubkey();
+    let limits_ix = |min_value, max_value| {
+        counter_ix(
+            program_id,
+            CounterInstruction::UpdateLimits { min_value, max_value },
+            &authority,
+            &counter,
+        )
+    };
+    let increment_ix = counter_ix(program_id, CounterInstruction::IncrementBy { amount: 5 }, &authority, &counter);
+    send(&mut context, &[increment_ix], &[]).await.unwrap();
+
+    // Widening lets the count past the old maximum
+    send(&mut context, &[limits_ix(0, 100)], &[]).await.unwrap();
+    let increment_ix = counter_ix(program_id, CounterInstruction::IncrementBy { amount: 20 }, &authority, &counter);
+    send(&mut context, &[increment_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 25);
+
+    // Narrowing around the count is allowed
+    send(&mut context, &[limits_ix(20, 30)], &[]).await.unwrap();
+    let counter_data = get_counter(&mut context, counter).await;
+    assert_eq!((counter_data.min_value, counter_data.max_value), (CounterValue(20), CounterValue(30)));
+
+    // Narrowing that excludes the count is not
+    let err = send(&mut context, &[limits_ix(0, 24)], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::ValueOutOfRange));
+    let err = send(&mut context, &[limits_ix(26, 30)], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::ValueOutOfRange));
+    let err = send(&mut context, &[limits_ix(30, 20)], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::InvalidLimits));
+
+    let counter_data = get_counter(&mut context, counter).await;
+    assert_eq!((counter_data.min_value, counter_data.max_value), (CounterValue(20), CounterValue(30)));
+}
+
+#[tokio::test]
+async fn test_rate_limit_covers_every_count_change() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithConfig {
+            config: CounterConfig {
+                rate_limited: true,
+                ..CounterConfig::default()
+            },
+        },
+    )
+    .await;
+    let authority = context.payer.pubkey();
+    let increment_ix = instruction_builders::increment(&program_id, &authority, &counter);
+
+    // Each follows an increment in the same slot
+    for instruction in [
+        CounterInstruction::Batch { ops: vec![1, 1] },
+        CounterInstruction::SetValue { value: 5 },
+        CounterInstruction::Reset,
+        CounterInstruction::Undo,
+    ] {
+        let second_ix = counter_ix(program_id, instruction, &authority, &counter);
+        let err = send(&mut context, &[increment_ix.clone(), second_ix], &[]).await.unwrap_err();
+        assert_eq!(err, counter_error_at(1, CounterError::RateLimited));
+    }
+    assert_eq!(get_counter(&mut context, counter).await.count, 0);
+}
+
+#[tokio::test]
+async fn test_set_value_starts_new_epoch_window() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithConfig {
+            config: CounterConfig {
+                epoch_window: true,
+                ..CounterConfig::default()
+            },
+        },
+    )
+    .await;
+    let authority = context.payer.pubkey();
+    let increment_ix = instruction_builders::increment(&program_id, &authority, &counter);
+    send(&mut context, std::slice::from_ref(&increment_ix), &[]).await.unwrap();
+
+    let clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    let next_epoch_slot = context
+        .genesis_config()
+        .epoch_schedule
+        .get_first_slot_in_epoch(clock.epoch + 1);
+    context.warp_to_slot(next_epoch_slot).unwrap();
+
+    // The set value opens the new window, so the increment after it keeps it
+    let set_ix = counter_ix(program_id, CounterInstruction::SetValue { value: 7 }, &authority, &counter);
+    send(&mut context, &[set_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.last_epoch, clock.epoch + 1);
+    send(&mut context, &[increment_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 8);
+}
+
+#[tokio::test]
+async fn test_decay_applies_before_every_change() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithConfig {
+            config: CounterConfig {
+                initial_value: Some(100),
+                decay_per_sec: 2,
+                ..CounterConfig::default()
+            },
+        },
+    )
+    .await;
+    let authority = context.payer.pubkey();
+    let last_updated = get_counter(&mut context, counter).await.last_updated;
+
+    // An empty batch changes nothing itself but still takes the accrued decay
+    advance_clock(&mut context, 30).await;
+    let clock: Clock = context.banks_client.get_sysvar().await.unwrap();
+    let elapsed = (clock.unix_timestamp - last_updated) as u64;
+    let batch_ix = counter_ix(program_id, CounterInstruction::Batch { ops: vec![] }, &authority, &counter);
+    send(&mut context, &[batch_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 100 - 2 * elapsed);
+}
+
+#[tokio::test]
+async fn test_replacing_the_count_emits_count_changed() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+    let ix = |instruction| counter_ix(program_id, instruction, &authority, &counter);
+
+    let logs = send_with_logs(
+        &mut context,
+        &[
+            ix(CounterInstruction::SetValue { value: 10 }),
+            ix(CounterInstruction::MultiplyBy { factor: 3 }),
+            ix(CounterInstruction::Undo),
+            ix(CounterInstruction::Batch { ops: vec![4, -1] }),
+            ix(CounterInstruction::CompareAndSet { expected: 13, new: 20 }),
+            ix(CounterInstruction::Reset),
+        ],
+        &[],
+    )
+    .await;
+
+    let changes = [(0, 10), (10, 30), (30, 10), (10, 13), (13, 20), (20, 0)];
+    assert_eq!(
+        extract_events(&logs),
+        changes
+            .map(|(old, new)| CounterEvent::CountChanged { counter, old, new })
+            .to_vec()
+    );
+}
+
+#[tokio::test]
+async fn test_count_changed_reports_stored_count() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithConfig {
+            config: CounterConfig {
+                initial_value: Some(100),
+                decay_per_sec: 1,
+                ..CounterConfig::default()
+            },
+        },
+    )
+    .await;
+    let authority = context.payer.pubkey();
+
+    // The reset reports the count as stored, before the decay it applied
+    advance_clock(&mut context, 30).await;
+    let reset_ix = counter_ix(program_id, CounterInstruction::ResetToInitial, &authority, &counter);
+    let logs = send_with_logs(&mut context, &[reset_ix], &[]).await;
+    assert_eq!(
+        extract_events(&logs),
+        vec![CounterEvent::CountChanged { counter, old: 100, new: 100 }]
+    );
+}
+
+#[tokio::test]
+async fn test_simulate_has_no_side_effects() {
+    let target = Pubkey::new_unique();
+    let mut program_test = ProgramTest::default();
+    program_test.add_program("milestone_target", target, processor!(record_milestone));
+    let (program_id, mut context) = start_program_with(program_test).await;
+    let authority = context.payer.pubkey();
+
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithConfig {
+            config: CounterConfig {
+                milestone: Some(1),
+                milestone_target: Some(target),
+                ..CounterConfig::default()
+            },
+        },
+    )
+    .await;
+    let mut simulate_ix = counter_ix(
+        program_id,
+        CounterInstruction::Simulate { inner: SimulatedInstruction::from(CounterInstruction::Increment) },
+        &authority,
+        &counter,
+    );
+    simulate_ix.accounts.push(AccountMeta::new_readonly(target, false));
+
+    // The simulated increment crosses the milestone, but neither emits nor calls back
+    let logs = send_with_logs(&mut context, &[simulate_ix], &[]).await;
+    assert_eq!(extract_events(&logs), vec![]);
+    assert!(!logs.iter().any(|line| line.contains("Milestone callback")));
+    let counter_data = get_counter(&mut context, counter).await;
+    assert_eq!(counter_data.count, 0);
+    assert!(!counter_data.milestone_fired);
+}
+
+#[tokio::test]
+async fn test_migrate_rejects_foreign_counter() {
+    let (program_id, mut context) = start_program().await;
+    let authority = context.payer.pubkey();
+
+    let mut account = AccountSharedData::new(1_000_000_000, CounterAccount::LEN, &Pubkey::new_unique());
+    account.set_data_from_slice(&sample_counter(authority).try_to_vec().unwrap());
+    let counter = Pubkey::new_unique();
+    context.set_account(&counter, &account);
+
+    let migrate_ix = counter_ix(program_id, CounterInstruction::Migrate, &authority, &counter);
+    let err = send(&mut context, &[migrate_ix], &[]).await.unwrap_err();
+    assert_eq!(err, TransactionError::InstructionError(0, InstructionError::IncorrectProgramId));
+}
+
+#[tokio::test]
+async fn test_decrement_to_zero_stops_at_min_value() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithConfig {
+            config: CounterConfig {
+                min_value: 5,
+                initial_value: Some(8),
+                ..CounterConfig::default()
+            },
+        },
+    )
+    .await;
+    let authority = context.payer.pubkey();
+
+    let decrement_ix = counter_ix(
+        program_id,
+        CounterInstruction::DecrementToZero { amount: 10 },
+        &authority,
+        &counter,
+    );
+    send(&mut context, &[decrement_ix], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 5);
+}
+
+#[tokio::test]
+async fn test_update_limits_rejects_signed_counter() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter_with(&mut context, program_id, CounterInstruction::InitializeSigned).await;
+    let authority = context.payer.pubkey();
+
+    let limits_ix = counter_ix(
+        program_id,
+        CounterInstruction::UpdateLimits { min_value: 0, max_value: 10 },
+        &authority,
+        &counter,
+    );
+    let err = send(&mut context, &[limits_ix], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::UnsupportedKind));
+    assert_eq!(get_counter(&mut context, counter).await.max_value, u64::MAX);
+}
+
+#[tokio::test]
+async fn test_undo_reverts_clamped_change() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithConfig {
+            config: CounterConfig {
+                mode: ArithmeticMode::Saturating,
+                ..CounterConfig::default()
+            },
+        },
+    )
+    .await;
+    let authority = context.payer.pubkey();
+    let ix = |instruction| counter_ix(program_id, instruction, &authority, &counter);
+
+    // Only the single step to u64::MAX was applied, so only that is undone
+    send(
+        &mut context,
+        &[ix(CounterInstruction::SetValue { value: u64::MAX - 1 }), ix(CounterInstruction::IncrementBy { amount: 5 })],
+        &[],
+    )
+    .await
+    .unwrap();
+    let counter_data = get_counter(&mut context, counter).await;
+    assert_eq!((counter_data.count, counter_data.last_delta), (CounterValue(u64::MAX), 1));
+    send(&mut context, &[ix(CounterInstruction::Undo)], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, u64::MAX - 1);
+
+    // Likewise for a decrement clamped at zero
+    send(
+        &mut context,
+        &[ix(CounterInstruction::SetValue { value: 2 }), ix(CounterInstruction::DecrementBy { amount: 5 })],
+        &[],
+    )
+    .await
+    .unwrap();
+    let counter_data = get_counter(&mut context, counter).await;
+    assert_eq!((counter_data.count, counter_data.last_delta), (CounterValue(0), -2));
+    send(&mut context, &[ix(CounterInstruction::Undo)], &[]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.count, 2);
+}
+
+#[tokio::test]
+async fn test_lone_authority_cannot_clear_multisig() {
+    let (program_id, mut context) = start_program().await;
+    let (counter, members) = create_multisig_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+
+    let clear_ix = || counter_ix(program_id, CounterInstruction::SetMultisig { multisig: None }, &authority, &counter);
+    let err = send(&mut context, &[clear_ix()], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::NotEnoughSigners));
+    assert!(get_counter(&mut context, counter).await.multisig.is_some());
+
+    // With two members co-signing, the authority may drop it
+    let mut clear_ix = clear_ix();
+    clear_ix.accounts.extend(members[..2].iter().map(|member| AccountMeta::new_readonly(member.pubkey(), true)));
+    send(&mut context, &[clear_ix], &[&members[0], &members[1]]).await.unwrap();
+    assert!(get_counter(&mut context, counter).await.multisig.is_none());
+}
+
+#[tokio::test]
+async fn test_delegate_cannot_bypass_multisig() {
+    let (program_id, mut context) = start_program().await;
+    let (counter, members) = create_multisig_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+    let delegate = Keypair::new();
+    let co_signed = |mut instruction: Instruction| {
+        instruction.accounts.extend(members[..2].iter().map(|member| AccountMeta::new_readonly(member.pubkey(), true)));
+        instruction
+    };
+
+    // The lone authority can't appoint a delegate
+    let set_ix = || {
+        counter_ix(program_id, CounterInstruction::SetDelegate { delegate: delegate.pubkey() }, &authority, &counter)
+    };
+    let err = send(&mut context, &[set_ix()], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::NotEnoughSigners));
+
+    // Nor can a delegate the multisig approved change the count alone
+    send(&mut context, &[co_signed(set_ix())], &[&members[0], &members[1]]).await.unwrap();
+    let increment_ix = counter_ix(program_id, CounterInstruction::Increment, &delegate.pubkey(), &counter);
+    let err = send(&mut context, &[increment_ix], &[&delegate]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::NotEnoughSigners));
+    assert_eq!(get_counter(&mut context, counter).await.count, 0);
+
+    // Revoking needs the multisig too
+    let revoke_ix = || counter_ix(program_id, CounterInstruction::RevokeDelegate, &authority, &counter);
+    let err = send(&mut context, &[revoke_ix()], &[]).await.unwrap_err();
+    assert_eq!(err, counter_error(CounterError::NotEnoughSigners));
+    send(&mut context, &[co_signed(revoke_ix())], &[&members[0], &members[1]]).await.unwrap();
+    assert_eq!(get_counter(&mut context, counter).await.delegate, None);
+}
+
+#[cfg(feature = "test-bpf")]
+#[tokio::test]
+async fn test_increment_by_cheaper_than_two_increments() {
+    let (program_id, mut context) = start_bpf_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+
+    let increment_by_ix = counter_ix(
+        program_id,
+        CounterInstruction::IncrementBy { amount: 2 },
+        &authority,
+        &counter,
+    );
+    let increment_by_units = compute_units(&mut context, &[increment_by_ix]).await;
+
+    let mut increment_units = 0;
+    for _ in 0..2 {
+        let increment_ix = instruction_builders::increment(&program_id, &authority, &counter);
+        increment_units += compute_units(&mut context, &[increment_ix]).await;
+    }
+
+    assert_eq!(get_counter(&mut context, counter).await.count, 4);
+    assert!(
+        increment_by_units < increment_units,
+        "IncrementBy used {} units, two Increments {}",
+        increment_by_units,
+        increment_units
+    );
+}
+
+#[cfg(feature = "test-bpf")]
+#[tokio::test]
+async fn test_plain_increment_skips_feature_checks() {
+    let (program_id, mut context) = start_bpf_program().await;
+    let authority = context.payer.pubkey();
+    let plain = create_counter(&mut context, program_id).await;
+    let featured = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithConfig {
+            config: CounterConfig {
+                rate_limited: true,
+                decay_per_sec: 1,
+                strict_signers: true,
+                ..CounterConfig::default()
+            },
+        },
+    )
+    .await;
+
+    let increment_ix = |counter| instruction_builders::increment(&program_id, &authority, &counter);
+    let plain_before = compute_units(&mut context, &[increment_ix(plain)]).await;
+    let featured_units = compute_units(&mut context, &[increment_ix(featured)]).await;
+    let plain_after = compute_units(&mut context, &[increment_ix(plain)]).await;
+
+    assert_eq!(get_counter(&mut context, plain).await.count, 2);
+    assert!(
+        plain_after <= plain_before,
+        "Plain increment used {} units, up from {}",
+        plain_after,
+        plain_before
+    );
+    assert!(
+        plain_before < featured_units,
+        "Plain increment used {} units, one with features {}",
+        plain_before,
+        featured_units
+    );
+}
+
+#[tokio::test]
+async fn test_scale_bounded_by_max_scale() {
+    let (program_id, mut context) = start_program().await;
+
+    let err = try_create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithConfig {
+            config: CounterConfig {
+                scale: MAX_SCALE + 1,
+                ..CounterConfig::default()
+            },
+        },
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err, counter_error_at(1, CounterError::InvalidScale));
+
+    let counter = create_counter_with(
+        &mut context,
+        program_id,
+        CounterInstruction::InitializeWithConfig {
+            config: CounterConfig {
+                scale: MAX_SCALE,
+                ..CounterConfig::default()
+            },
+        },
+    )
+    .await;
+    let counter_data = get_counter(&mut context, counter).await;
+    assert_eq!(counter_data.scale, MAX_SCALE);
+
+    // Every digit of the largest count still shows, and larger scales render the same
+    assert_eq!(to_decimal(u64::MAX, MAX_SCALE), "1.8446744073709551615");
+    assert_eq!(to_decimal(5, u32::MAX), to_decimal(5, MAX_SCALE));
+}
+
+#[tokio::test]
+async fn test_frozen_counter_can_be_closed() {
+    let (program_id, mut context) = start_program().await;
+    let counter = create_counter(&mut context, program_id).await;
+    let authority = context.payer.pubkey();
+    let destination = Pubkey::new_unique();
+
+    let surplus = 1_000_000;
+    let fund_ix = system_instruction::transfer(&authority, &counter, surplus);
+    let freeze_ix = counter_ix(program_id, CounterInstruction::Freeze, &authority, &counter);
+    send(&mut context, &[fund_ix, freeze_ix], &[]).await.unwrap();
+
+    let lamport_ix = |instruction| {
+        let mut lamport_ix = counter_ix(program_id, instruction, &authority, &counter);
+        lamport_ix.accounts.push(AccountMeta::new(destination, false));
+        lamport_ix
+    };
+
+    send(&mut context, &[lamport_ix(CounterInstruction::WithdrawExcessLamports)], &[]).await.unwrap();
+    assert_eq!(context.banks_client.get_balance(destination).await.unwrap(), surplus);
+    assert!(get_counter(&mut context, counter).await.frozen);
+
+    send(&mut context, &[lamport_ix(CounterInstruction::CloseCounter)], &[]).await.unwrap();
+    let rent_exemption = Rent::default().minimum_balance(CounterAccount::LEN);
+    assert_eq!(context.banks_client.get_balance(destination).await.unwrap(), surplus + rent_exemption);
+    assert!(context.banks_client.get_account(counter).await.unwrap().is_none());
+}